@@ -1,17 +1,135 @@
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait};
+
+use kira::clock::{ClockHandle, ClockSpeed};
+use kira::effect::eq_filter::{EqFilterBuilder, EqFilterHandle, EqFilterKind};
 use kira::sound::streaming::{StreamingSoundData, StreamingSoundHandle};
 use kira::sound::{FromFileError, PlaybackState};
+use kira::track::{TrackBuilder, TrackHandle};
 use kira::tween::Tween;
+use kira::{StartTime, Volume};
 
-use kira::manager::{backend::cpal::CpalBackend, AudioManager, AudioManagerSettings};
+use kira::manager::{
+    backend::cpal::{CpalBackend, CpalBackendSettings},
+    backend::mock::{MockBackend, MockBackendSettings},
+    error::PlaySoundError,
+    AudioManager, AudioManagerSettings,
+};
+use kira::ResourceLimitReached;
 
+use crate::channel::{ChannelMixBuilder, ChannelMixHandle};
+use crate::equalizer::EQ_BANDS;
+use crate::pitch::{PitchShiftBuilder, PitchShiftHandle};
+use crate::replaygain::{ReplayGain, ReplayGainConfig};
 use crate::util;
+use crate::visualizer::SpectrumTap;
+
+const EQ_Q: f64 = 1.41; // 一个倍频程带宽
+const EQ_TWEEN: f32 = 0.05; // 均衡器调节的过渡时间，避免爆音
+const MOCK_SAMPLE_RATE: u32 = 44100;
+const POSITION_TICK: f32 = 0.25; // 播放位置事件的最小间隔，单位：s
+
+/// 播放过程中发生的事件，由 poll_events 依次取出
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioEvent {
+    Started(String),       // 歌曲开始播放，包括无缝衔接切换到的下一首
+    Ended(String),         // 歌曲播放到结尾，且没有排队的下一首
+    Error(String, String, bool), // 歌曲无法打开或播放中出错，(路径, 错误信息, 是否正要播放)
+    Position(f32),         // 播放位置，播放期间定期发出
+}
+
+/// 音频管理器，没有可用的音频设备时使用不输出声音的模拟后端，保证界面和曲库照常可用
+enum Manager {
+    // 两种后端大小相差很大，都装箱避免枚举按最大的变体占用空间
+    Cpal(Box<AudioManager<CpalBackend>>),
+    Mock(Box<AudioManager<MockBackend>>),
+}
+impl Manager {
+    fn mock() -> Manager {
+        let settings = AudioManagerSettings {
+            backend_settings: MockBackendSettings {
+                sample_rate: MOCK_SAMPLE_RATE,
+            },
+            ..Default::default()
+        };
+        Manager::Mock(Box::new(AudioManager::<MockBackend>::new(settings).unwrap()))
+    }
+
+    fn play(
+        &mut self,
+        sound_data: StreamingSoundData<FromFileError>,
+    ) -> Result<StreamingSoundHandle<FromFileError>, PlaySoundError<FromFileError>> {
+        match self {
+            Manager::Cpal(manager) => manager.play(sound_data),
+            Manager::Mock(manager) => manager.play(sound_data),
+        }
+    }
+
+    fn add_clock(&mut self) -> Result<ClockHandle, ResourceLimitReached> {
+        let speed = ClockSpeed::TicksPerSecond(1.0);
+        match self {
+            Manager::Cpal(manager) => manager.add_clock(speed),
+            Manager::Mock(manager) => manager.add_clock(speed),
+        }
+    }
+
+    fn add_sub_track(&mut self, builder: TrackBuilder) -> Result<TrackHandle, ResourceLimitReached> {
+        match self {
+            Manager::Cpal(manager) => manager.add_sub_track(builder),
+            Manager::Mock(manager) => manager.add_sub_track(builder),
+        }
+    }
+}
+
+/// 预加载的下一首，按时钟时间排队等待无缝衔接
+struct NextSound {
+    path: String,
+    duration: f32,
+    start: f64, // 开始播放的时钟时间，单位：s
+    crossfade: bool, // 是否与当前歌曲交叉淡入淡出
+    gain: f32,
+    track_gain: ReplayGain, // 切换为当前歌曲后沿用，不再重新读取标签
+    handle: StreamingSoundHandle<FromFileError>,
+}
+
+/// 效果音轨，所有歌曲都输出到挂载了声道效果器、变调器、均衡器和采样分接器的子音轨上
+struct EffectTrack {
+    track: TrackHandle,
+    channel: ChannelMixHandle,
+    pitch: PitchShiftHandle,
+    bands: Vec<EqFilterHandle>,
+}
 
 pub struct Audio {
+    path: String,
     duration: f32,
     volume: f32,
+    gain: f32, // 响度均衡增益，与音量相乘
+    track_gain: ReplayGain, // 当前歌曲的 ReplayGain 信息，跳转等重新打开同一首时复用
+    replay_gain: ReplayGainConfig,
     status: PlaybackState,
-    manager: AudioManager,
+    manager: Manager,
+    clock: Option<ClockHandle>, // 播放时钟，与当前歌曲同步走动，用于精确安排下一首的开始时间
     sound_handle: Option<StreamingSoundHandle<FromFileError>>,
+    next_sound: Option<NextSound>,
+    fading: Vec<StreamingSoundHandle<FromFileError>>, // 正在淡出的上一首
+    events: Vec<AudioEvent>, // 等待取出的事件
+    ended: bool,             // 当前歌曲已发出结束事件
+    play_requested: bool,    // 正在打开的歌曲打开后是否播放，打开失败时随错误事件发出
+    last_position: f32,      // 上次发出位置事件时的播放位置
+    effect_track: Option<EffectTrack>,
+    speed: f32, // 播放速度倍率
+    loop_region: Option<(f32, f32)>, // A-B 循环区间，单位：s
+    device: Option<String>, // 正在使用的输出设备，None 为系统默认设备
+    eq_gains: Vec<f32>,
+    eq_preamp: f32,
+    keep_pitch: bool,
+    click_fade: f32, // 暂停、继续、停止和跳转时的淡入淡出时长，避免爆音，单位：s
+    balance: f32, // 声道平衡，-1 为最左，1 为最右
+    mono: bool, // 单声道混音
+    swap_channels: bool, // 左右声道互换
+    spectrum: SpectrumTap, // 可视化用的输出采样，切换设备后沿用
 }
 
 
@@ -23,72 +141,482 @@ impl Default for Audio {
 
 impl Audio {
     pub fn new() -> Audio {
-        let manager = AudioManager::<CpalBackend>::new(AudioManagerSettings::default()).unwrap();
+        let mut manager = match create_manager("") {
+            Some((manager, _)) => manager,
+            None => {
+                util::log_err("no audio device, use mock backend");
+                Manager::mock()
+            }
+        };
+        let clock = add_clock(&mut manager);
+        let spectrum = SpectrumTap::default();
+        let effect_track = add_effect_track(&mut manager, &spectrum);
         Audio {
+            path: String::new(),
             duration: 0.,
             volume: 1.0,
+            gain: 1.0,
+            track_gain: ReplayGain::default(),
+            replay_gain: ReplayGainConfig::default(),
             manager,
+            clock,
             status: PlaybackState::Stopped,
             sound_handle: None,
+            next_sound: None,
+            fading: vec![],
+            events: vec![],
+            ended: false,
+            play_requested: false,
+            last_position: 0.,
+            effect_track,
+            speed: 1.0,
+            loop_region: None,
+            device: None,
+            eq_gains: vec![],
+            eq_preamp: 0.,
+            keep_pitch: false,
+            click_fade: 0.,
+            balance: 0.,
+            mono: false,
+            swap_channels: false,
+            spectrum,
         }
     }
 
-    pub fn stop(&mut self) {
+    /// 没有可用的音频设备，正在使用模拟后端
+    pub fn is_offline(&self) -> bool {
+        matches!(self.manager, Manager::Mock(_))
+    }
+
+    /// 正在使用的输出设备，None 为系统默认设备
+    pub fn device(&self) -> Option<&String> {
+        self.device.as_ref()
+    }
+
+    /// 切换输出设备，并在新设备上从当前位置继续播放
+    ///
+    /// 设备不存在或打开失败时退回系统默认设备，name 为空表示使用系统默认设备
+    pub fn set_output_device(&mut self, name: &str) {
+        let (manager, device) = match create_manager(name) {
+            Some(result) => result,
+            None => return,
+        };
+        let path = self.path.to_string();
+        let position = self.position();
+        let is_play = self.is_play();
+        let loop_region = self.loop_region;
+
+        self.stop();
+        self.manager = manager;
+        self.device = device;
+        self.clock = add_clock(&mut self.manager);
+        self.effect_track = add_effect_track(&mut self.manager, &self.spectrum);
+        let (gains, preamp) = (self.eq_gains.to_vec(), self.eq_preamp);
+        self.set_equalizer(&gains, preamp);
+        self.set_speed(self.speed, self.keep_pitch);
+        self.set_channels(self.balance, self.mono, self.swap_channels);
+        util::log(format!("output device {:?}", self.device));
+
+        if path.is_empty() || !self.open(&path, None) {
+            return;
+        }
         if let Some(ref mut sound_handle) = self.sound_handle {
+            sound_handle.seek_to(position as f64);
+        }
+        self.set_loop_region(loop_region);
+        if is_play {
+            self.status = PlaybackState::Playing;
+            if let Some(ref mut clock) = self.clock {
+                clock.start();
+            }
+        } else {
+            self.pause();
+        }
+    }
+
+    pub fn set_click_fade(&mut self, secs: f32) {
+        self.click_fade = secs.max(0.);
+    }
+    fn click_tween(&self) -> Tween {
+        if self.click_fade > 0. {
+            fade_tween(self.click_fade)
+        } else {
+            Tween::default()
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.clear_next();
+        self.clear_started();
+        for mut sound_handle in self.fading.drain(..) {
             sound_handle.stop(Tween::default());
+        }
+        if let Some(ref mut clock) = self.clock {
+            clock.pause();
+        }
+        let tween = self.click_tween();
+        if let Some(ref mut sound_handle) = self.sound_handle {
+            // 停止的淡出在后台完成，句柄丢弃后声音仍会继续到淡出结束
+            sound_handle.stop(tween);
             self.sound_handle = None;
             self.status = PlaybackState::Stopped;
         }
     }
 
-    pub fn start_play(&mut self, path: &String, go_play: bool) {
+    /// 打开歌曲，打开失败时返回 false，错误随 Error 事件发出
+    pub fn start_play(&mut self, path: &str, go_play: bool) -> bool {
         self.stop();
         if let Some(sound_handle) = &self.sound_handle {
             if sound_handle.state() == PlaybackState::Playing {
-                return;
+                return false;
             }
         }
 
-        if let Ok(sound_data) = StreamingSoundData::from_file(path) {
-            // self.sound_data = Some(sound_data);
-            self.duration = sound_data.duration().as_secs_f32();
+        self.play_requested = go_play;
+        if !self.open(path, None) {
+            return false;
+        }
+        self.events.push(AudioEvent::Started(path.to_string()));
+        if go_play {
+            self.status = PlaybackState::Playing;
+            if let Some(ref mut clock) = self.clock {
+                clock.start();
+            }
+        } else {
+            self.pause()
+        }
+        true
+    }
 
-            let mut play = self.manager.play(sound_data).unwrap();
-            play.set_volume(self.volume as f64, Tween::default());
-            self.sound_handle = Some(play);
+    /// 手动切歌时交叉淡入淡出，当前歌曲淡出的同时新歌曲淡入，打开失败时返回 false
+    pub fn cross_play(&mut self, path: &str, fade: f32) -> bool {
+        if fade <= 0. || !self.is_play() {
+            return self.start_play(path, true);
+        }
 
-            if go_play {
-                self.status = PlaybackState::Playing;
-            } else {
-                self.pause()
+        self.clear_next();
+        self.clear_started();
+        let tween = fade_tween(fade);
+        if let Some(mut sound_handle) = self.sound_handle.take() {
+            sound_handle.stop(tween);
+            self.fading.push(sound_handle);
+        }
+        self.play_requested = true;
+        if self.open(path, Some(tween)) {
+            self.events.push(AudioEvent::Started(path.to_string()));
+            self.status = PlaybackState::Playing;
+            true
+        } else {
+            self.status = PlaybackState::Stopped;
+            false
+        }
+    }
+
+    fn open(&mut self, path: &str, fade_in: Option<Tween>) -> bool {
+        self.open_at(path, 0., fade_in)
+    }
+
+    /// 从 position 秒处开始打开歌曲
+    fn open_at(&mut self, path: &str, position: f32, fade_in: Option<Tween>) -> bool {
+        let sound_data = match StreamingSoundData::from_file(path) {
+            Ok(sound_data) => sound_data,
+            Err(err) => {
+                self.open_failed(path, err.to_string());
+                return false;
+            }
+        };
+        self.duration = sound_data.duration().as_secs_f32();
+        if self.path != path {
+            self.path = path.to_string();
+            self.track_gain = ReplayGain::read(path);
+        }
+        self.gain = self.track_gain.factor(&self.replay_gain);
+        self.loop_region = None;
+        self.ended = false;
+        self.last_position = position;
+
+        let mut sound_data = sound_data
+            .volume(self.amplitude())
+            .playback_rate(self.speed as f64)
+            .start_position(position as f64)
+            .panning(self.sound_panning())
+            .fade_in_tween(fade_in);
+        if let Some(ref effect_track) = self.effect_track {
+            sound_data = sound_data.output_destination(&effect_track.track);
+        }
+        match self.manager.play(sound_data) {
+            Ok(play) => self.sound_handle = Some(play),
+            Err(err) => {
+                self.open_failed(path, err.to_string());
+                return false;
             }
+        }
+
+        util::log(format!("sink append {}", path));
+        true
+    }
+
+    fn open_failed(&mut self, path: &str, err: String) {
+        self.events.push(AudioEvent::Error(
+            path.to_string(),
+            err,
+            self.play_requested,
+        ));
+    }
 
-            util::log(format!("sink append {}", path));
+    /// 预加载下一首，并安排在当前歌曲结束的时刻开始播放
+    ///
+    /// crossfade 大于 0 时提前开始，两首歌曲在这段时间内交叉淡入淡出
+    pub fn queue_next(&mut self, path: &str, crossfade: f32) -> bool {
+        self.clear_next();
+        if self.sound_handle.is_none() {
+            return false;
+        }
+        let now = if let Some(ref clock) = self.clock {
+            clock.time()
+        } else {
+            return false;
+        };
+
+        let sound_data = match StreamingSoundData::from_file(path) {
+            Ok(sound_data) => sound_data,
+            Err(err) => {
+                util::log_err(format!("preload next error path={} err={}", path, err));
+                return false;
+            }
+        };
+        let duration = sound_data.duration().as_secs_f32();
+        let track_gain = ReplayGain::read(path);
+        let gain = track_gain.factor(&self.replay_gain);
+        // 变速播放时按实际经过的时间计算
+        let remain = (self.duration - self.position()).max(0.) / self.speed;
+        let crossfade = crossfade.min(remain).min(duration / self.speed / 2.).max(0.);
+        let delay = (remain - crossfade) as f64;
+        let start = now.ticks as f64 + now.fraction + delay;
+        let mut sound_data = sound_data
+            .start_time(now + delay)
+            .volume((self.volume * gain) as f64)
+            .playback_rate(self.speed as f64)
+            .panning(self.sound_panning());
+        if crossfade > 0. {
+            sound_data = sound_data.fade_in_tween(fade_tween(crossfade));
+        }
+        if let Some(ref effect_track) = self.effect_track {
+            sound_data = sound_data.output_destination(&effect_track.track);
+        }
+        match self.manager.play(sound_data) {
+            Ok(handle) => {
+                if crossfade > 0. {
+                    if let Some(ref mut sound_handle) = self.sound_handle {
+                        sound_handle.set_volume(
+                            0.,
+                            Tween {
+                                start_time: StartTime::ClockTime(now + delay),
+                                ..fade_tween(crossfade)
+                            },
+                        );
+                    }
+                }
+                self.next_sound = Some(NextSound {
+                    path: path.to_string(),
+                    duration,
+                    start,
+                    crossfade: crossfade > 0.,
+                    gain,
+                    track_gain,
+                    handle,
+                });
+                util::log(format!(
+                    "queue next {} after {:.3}s crossfade {:.1}s",
+                    path, delay, crossfade
+                ));
+                true
+            }
+            Err(err) => {
+                util::log_err(format!("queue next error path={} err={}", path, err));
+                false
+            }
+        }
+    }
+
+    /// 取消已预加载的下一首
+    pub fn clear_next(&mut self) {
+        if let Some(mut next_sound) = self.next_sound.take() {
+            next_sound.handle.stop(Tween::default());
+            if next_sound.crossfade {
+                // 撤销已安排的淡出
+                let amplitude = self.amplitude();
+                if let Some(ref mut sound_handle) = self.sound_handle {
+                    sound_handle.set_volume(amplitude, Tween::default());
+                }
+            }
+        }
+    }
+
+    pub fn next_path(&self) -> Option<&str> {
+        self.next_sound.as_ref().map(|next_sound| next_sound.path.as_str())
+    }
+
+    /// 取出自上次以来发生的事件：下一首到点后切换为当前歌曲并发出开始事件，
+    /// 检查播放错误、播放结束，并定期发出播放位置
+    pub fn poll_events(&mut self) -> Vec<AudioEvent> {
+        self.fading
+            .retain(|sound_handle| sound_handle.state() != PlaybackState::Stopped);
+        self.promote_next();
+        if let Some(ref mut sound_handle) = self.sound_handle {
+            if let Some(err) = sound_handle.pop_error() {
+                let playing = self.status == PlaybackState::Playing;
+                self.events
+                    .push(AudioEvent::Error(self.path.to_string(), err.to_string(), playing));
+            }
+        }
+        if self.is_play() {
+            let position = self.position();
+            if (position - self.last_position).abs() >= POSITION_TICK {
+                self.last_position = position;
+                self.events.push(AudioEvent::Position(position));
+            }
+            // 已排队无缝衔接的下一首会自行接上，不算结束
+            if !self.ended && self.is_over() && self.next_path().is_none() {
+                self.ended = true;
+                self.events.push(AudioEvent::Ended(self.path.to_string()));
+            }
+        }
+        std::mem::take(&mut self.events)
+    }
+    /// 还有未取出的事件
+    pub fn has_events(&self) -> bool {
+        !self.events.is_empty()
+    }
+    /// 停止或切歌后，尚未取出的开始事件已过时
+    fn clear_started(&mut self) {
+        self.events
+            .retain(|event| !matches!(event, AudioEvent::Started(_)));
+    }
+
+    fn promote_next(&mut self) {
+        let clock_time = if let Some(ref clock) = self.clock {
+            let time = clock.time();
+            time.ticks as f64 + time.fraction
+        } else {
+            return;
+        };
+        let started = match self.next_sound {
+            Some(ref next_sound) => clock_time >= next_sound.start,
+            None => false,
+        };
+        if !started {
+            return;
+        }
+
+        if let Some(next_sound) = self.next_sound.take() {
+            if let Some(mut sound_handle) = self.sound_handle.take() {
+                if next_sound.crossfade {
+                    // 上一首余下的部分继续淡出直至结束
+                    self.fading.push(sound_handle);
+                } else {
+                    sound_handle.stop(Tween::default());
+                }
+            }
+            self.duration = next_sound.duration;
+            self.gain = next_sound.gain;
+            self.track_gain = next_sound.track_gain;
+            self.path = next_sound.path.to_string();
+            self.loop_region = None;
+            self.sound_handle = Some(next_sound.handle);
+            self.status = PlaybackState::Playing;
+            self.ended = false;
+            self.last_position = 0.;
+            self.events.push(AudioEvent::Started(next_sound.path));
         }
     }
 
     pub fn pause(&mut self) {
+        // 交叉淡入淡出期间下一首已开始，先切换过去再暂停
+        self.promote_next();
+        for sound_handle in self.fading.iter_mut() {
+            sound_handle.stop(Tween::default());
+        }
+        let tween = self.click_tween();
         if let Some(ref mut sound_handle) = self.sound_handle {
-            sound_handle.pause(Tween::default());
+            // 淡出期间声音仍在播放，但状态已是暂停，再次继续时从淡出处恢复
+            sound_handle.pause(tween);
             self.status = PlaybackState::Paused;
             util::log("pause sink");
         }
+        if let Some(ref mut clock) = self.clock {
+            clock.pause();
+        }
     }
 
     pub fn toggle_play(&mut self) {
         if self.is_play() {
             self.pause();
+        } else if self.sound_handle.is_none() {
+            // 已停止时重新打开当前歌曲
+            if !self.path.is_empty() {
+                let path = self.path.to_string();
+                self.start_play(&path, true);
+            }
+        } else if self.is_over() {
+            // 已播放到结尾（如定时关闭在歌曲结束时暂停），继续播放时发出结束事件，切到下一首
+            self.status = PlaybackState::Playing;
+            self.ended = true;
+            self.events.push(AudioEvent::Ended(self.path.to_string()));
         } else {
+            let tween = self.click_tween();
             if let Some(ref mut sound_handle) = self.sound_handle {
-                sound_handle.resume(Tween::default());
+                sound_handle.resume(tween);
                 self.status = PlaybackState::Playing;
+                if let Some(ref mut clock) = self.clock {
+                    clock.start();
+                }
             }
         }
     }
 
     pub fn seek(&mut self, pos: f32) {
+        // 跳转后结束时刻已变，已排队的下一首作废，等待重新预加载
+        self.clear_next();
+        self.ended = false;
+        let finished = self
+            .sound_handle
+            .as_ref()
+            .is_some_and(|sound_handle| sound_handle.state() == PlaybackState::Stopped);
+        if finished && !self.path.is_empty() {
+            // 已播放到结尾的声音无法跳转，从新位置重新打开
+            let (path, loop_region) = (self.path.to_string(), self.loop_region);
+            if self.open_at(&path, pos, Some(self.click_tween())) {
+                self.set_loop_region(loop_region);
+                if !self.is_play() {
+                    if let Some(ref mut sound_handle) = self.sound_handle {
+                        sound_handle.pause(Tween::default());
+                    }
+                }
+            } else {
+                self.status = PlaybackState::Stopped;
+            }
+            return;
+        }
+
+        let dip = self.is_play() && self.click_fade > 0.;
+        let tween = self.click_tween();
+        let amplitude = self.amplitude();
         if let Some(ref mut sound_handle) = self.sound_handle {
-            sound_handle.seek_to(pos as f64);
+            if dip {
+                // 播放中跳转：短暂压低音量，跳转后再恢复，避免爆音
+                sound_handle.set_volume(0., tween);
+                sound_handle.seek_to(pos as f64);
+                sound_handle.set_volume(
+                    amplitude,
+                    Tween {
+                        start_time: StartTime::Delayed(tween.duration),
+                        ..tween
+                    },
+                );
+            } else {
+                sound_handle.seek_to(pos as f64);
+            }
         }
     }
 
@@ -121,9 +649,241 @@ impl Audio {
         if self.volume > 1. {
             return;
         }
+        self.volume = volume;
+        self.apply_volume();
+    }
+
+    fn amplitude(&self) -> f64 {
+        (self.volume * self.gain) as f64
+    }
+
+    fn apply_volume(&mut self) {
+        let crossfade = self
+            .next_sound
+            .as_ref()
+            .is_some_and(|next_sound| next_sound.crossfade);
+        let amplitude = self.amplitude();
         if let Some(ref mut sound_handle) = self.sound_handle {
-            sound_handle.set_volume(volume as f64, Tween::default())
+            // 已安排交叉淡出时不覆盖淡出的音量变化
+            if !crossfade {
+                sound_handle.set_volume(amplitude, Tween::default())
+            }
         }
-        self.volume = volume;
+        if let Some(ref mut next_sound) = self.next_sound {
+            let amplitude = (self.volume * next_sound.gain) as f64;
+            next_sound.handle.set_volume(amplitude, Tween::default())
+        }
+    }
+
+    /// 更新响度均衡设置，并重新计算当前歌曲的增益
+    pub fn set_replay_gain(&mut self, replay_gain: ReplayGainConfig) {
+        self.replay_gain = replay_gain;
+        self.gain = if self.path.is_empty() {
+            1.0
+        } else {
+            self.track_gain.factor(&self.replay_gain)
+        };
+        if let Some(ref mut next_sound) = self.next_sound {
+            next_sound.gain = next_sound.track_gain.factor(&self.replay_gain);
+        }
+        self.apply_volume();
+    }
+
+    /// 在 secs 秒内将当前歌曲淡出至静音
+    pub fn fade_out(&mut self, secs: f32) {
+        self.clear_next();
+        if let Some(ref mut sound_handle) = self.sound_handle {
+            sound_handle.set_volume(0., fade_tween(secs));
+        }
+    }
+    /// 恢复淡出前的音量
+    pub fn restore_volume(&mut self) {
+        self.apply_volume();
+    }
+
+    pub fn loop_region(&self) -> Option<(f32, f32)> {
+        self.loop_region
+    }
+    /// 设置 A-B 循环区间，None 为取消循环
+    pub fn set_loop_region(&mut self, region: Option<(f32, f32)>) {
+        self.loop_region = region;
+        let position = self.position();
+        if let Some(ref mut sound_handle) = self.sound_handle {
+            match region {
+                Some((start, end)) => {
+                    sound_handle.set_loop_region(start as f64..end as f64);
+                    if position >= end {
+                        sound_handle.seek_to(start as f64);
+                    }
+                }
+                None => sound_handle.set_loop_region(None),
+            }
+        }
+        // 循环期间歌曲不会结束，已排队的下一首作废
+        self.clear_next();
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+    /// 设置播放速度，keep_pitch 为 true 时通过变调抵消音高的变化
+    pub fn set_speed(&mut self, speed: f32, keep_pitch: bool) {
+        self.speed = speed;
+        self.keep_pitch = keep_pitch;
+        if let Some(ref mut sound_handle) = self.sound_handle {
+            sound_handle.set_playback_rate(speed as f64, Tween::default());
+        }
+        if let Some(ref mut effect_track) = self.effect_track {
+            effect_track
+                .pitch
+                .set_ratio(if keep_pitch { 1. / speed } else { 1. });
+        }
+        // 速度变化后结束时刻已变，已排队的下一首作废
+        self.clear_next();
+    }
+
+    pub fn spectrum(&self) -> &SpectrumTap {
+        &self.spectrum
+    }
+
+    /// 设置声道平衡、单声道混音和左右声道互换
+    pub fn set_channels(&mut self, balance: f32, mono: bool, swap: bool) {
+        self.balance = balance.clamp(-1., 1.);
+        self.mono = mono;
+        self.swap_channels = swap;
+        let panning = self.sound_panning();
+        let tween = fade_tween(EQ_TWEEN);
+        let handles = self
+            .sound_handle
+            .iter_mut()
+            .chain(self.next_sound.iter_mut().map(|next_sound| &mut next_sound.handle))
+            .chain(self.fading.iter_mut());
+        for sound_handle in handles {
+            sound_handle.set_panning(panning, tween);
+        }
+        if let Some(ref mut effect_track) = self.effect_track {
+            effect_track
+                .channel
+                .set(mono, swap, (self.balance + 1.) / 2.);
+        }
+    }
+    /// 歌曲的 panning，0 为最左，1 为最右；声道互换在效果器中进行，这里先反向平衡，
+    /// 单声道时由效果器在混音之后平衡
+    fn sound_panning(&self) -> f64 {
+        let panning = (self.balance as f64 + 1.) / 2.;
+        if self.mono {
+            0.5
+        } else if self.swap_channels {
+            1. - panning
+        } else {
+            panning
+        }
+    }
+
+    /// 设置均衡器各频段增益和前置增益，单位：dB
+    pub fn set_equalizer(&mut self, gains: &[f32], preamp: f32) {
+        self.eq_gains = gains.to_vec();
+        self.eq_preamp = preamp;
+        if let Some(ref mut effect_track) = self.effect_track {
+            for (band, gain) in effect_track.bands.iter_mut().zip(gains) {
+                band.set_gain(*gain as f64, fade_tween(EQ_TWEEN));
+            }
+            effect_track
+                .track
+                .set_volume(Volume::Decibels(preamp as f64), fade_tween(EQ_TWEEN));
+        }
+    }
+}
+
+/// 列出所有输出设备的名称
+pub fn output_devices() -> Vec<String> {
+    match cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(err) => {
+            util::log_err(format!("list output devices error: {}", err));
+            vec![]
+        }
+    }
+}
+
+/// 按设备名创建音频管理器，返回实际使用的设备
+fn create_manager(name: &str) -> Option<(Manager, Option<String>)> {
+    let device = if name.is_empty() {
+        None
+    } else {
+        let device = cpal::default_host().output_devices().ok().and_then(|mut devices| {
+            devices.find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        });
+        if device.is_none() {
+            util::log_err(format!("output device {} not found, use default", name));
+        }
+        device
+    };
+    if device.is_some() {
+        let settings = AudioManagerSettings {
+            backend_settings: CpalBackendSettings {
+                device,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        match AudioManager::<CpalBackend>::new(settings) {
+            Ok(manager) => return Some((Manager::Cpal(Box::new(manager)), Some(name.to_string()))),
+            Err(err) => util::log_err(format!("open output device {} error: {}", name, err)),
+        }
+    }
+    match AudioManager::<CpalBackend>::new(AudioManagerSettings::default()) {
+        Ok(manager) => Some((Manager::Cpal(Box::new(manager)), None)),
+        Err(err) => {
+            util::log_err(format!("open default output device error: {}", err));
+            None
+        }
+    }
+}
+
+fn add_clock(manager: &mut Manager) -> Option<ClockHandle> {
+    match manager.add_clock() {
+        Ok(clock) => Some(clock),
+        Err(err) => {
+            util::log_err(format!("add audio clock error: {}", err));
+            None
+        }
+    }
+}
+
+fn add_effect_track(manager: &mut Manager, spectrum: &SpectrumTap) -> Option<EffectTrack> {
+    let mut builder = TrackBuilder::new();
+    let channel = builder.add_effect(ChannelMixBuilder);
+    let pitch = builder.add_effect(PitchShiftBuilder);
+    let bands = EQ_BANDS
+        .iter()
+        .map(|freq| {
+            builder.add_effect(EqFilterBuilder::new(
+                EqFilterKind::Bell,
+                *freq as f64,
+                0.,
+                EQ_Q,
+            ))
+        })
+        .collect();
+    builder.add_effect(spectrum.clone());
+    match manager.add_sub_track(builder) {
+        Ok(track) => Some(EffectTrack {
+            track,
+            channel,
+            pitch,
+            bands,
+        }),
+        Err(err) => {
+            util::log_err(format!("add effect track error: {}", err));
+            None
+        }
+    }
+}
+
+fn fade_tween(secs: f32) -> Tween {
+    Tween {
+        duration: Duration::from_secs_f32(secs),
+        ..Default::default()
     }
 }
@@ -0,0 +1,118 @@
+use iced::{
+    theme,
+    widget::{button, column, container, row, text, text_input, Scrollable},
+    Alignment, Length,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::button_icon,
+    style::{self, ButtonType},
+    util, Message, SilkPlayer, SongControl, View,
+};
+
+/// 歌曲中的书签，按文件路径保存在歌曲记录中
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub position: f32, // 单位：s
+}
+
+#[derive(Debug, Clone)]
+pub enum BookmarkControl {
+    Name(String), // 输入书签名称
+    Add,          // 在当前位置添加书签，未输入名称时以时间命名
+    Remove(usize),
+}
+
+impl SilkPlayer {
+    /// 当前歌曲的书签，按位置排序
+    pub fn bookmarks(&self) -> &[Bookmark] {
+        self.track_store
+            .get(&self.current_song.path)
+            .map_or(&[], |record| &record.bookmarks)
+    }
+
+    pub fn bookmark_control(&mut self, control: BookmarkControl) {
+        let path = self.current_song.path.to_string();
+        match control {
+            BookmarkControl::Name(value) => self.app_control.bookmark_name = value,
+            BookmarkControl::Add => {
+                if path.is_empty() {
+                    return;
+                }
+                let position = self.audio.position();
+                let name = match self.app_control.bookmark_name.trim() {
+                    "" => format!("书签 {}", util::play_time(position)),
+                    name => name.to_string(),
+                };
+                util::log(format!("add bookmark {} at {}", name, position));
+                self.track_store.update(&path, |record| {
+                    record.bookmarks.push(Bookmark { name, position });
+                    record
+                        .bookmarks
+                        .sort_by(|a, b| a.position.total_cmp(&b.position));
+                });
+                self.app_control.bookmark_name.clear();
+            }
+            BookmarkControl::Remove(index) => {
+                self.track_store.update(&path, |record| {
+                    if index < record.bookmarks.len() {
+                        record.bookmarks.remove(index);
+                    }
+                });
+            }
+        }
+    }
+
+    pub fn detail_bookmarks(&self) -> View<'_> {
+        let add = row!(
+            text_input("书签名称，可留空", &self.app_control.bookmark_name)
+                .on_input(|value| Message::Bookmark(BookmarkControl::Name(value)))
+                .on_submit(Message::Bookmark(BookmarkControl::Add))
+                .size(16),
+            button(text(format!("在 {} 添加", util::play_time(self.audio.position()))).size(14))
+                .on_press(Message::Bookmark(BookmarkControl::Add))
+                .style(theme::Button::Custom(Box::new(ButtonType::Primary.default()))),
+        )
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let mut list = column!().spacing(5);
+        for (index, bookmark) in self.bookmarks().iter().enumerate() {
+            list = list.push(
+                row!(
+                    button(
+                        row!(
+                            text(util::play_time(bookmark.position)).size(14),
+                            text(&bookmark.name).size(18),
+                        )
+                        .spacing(15)
+                        .align_items(Alignment::Center),
+                    )
+                    .width(Length::Fill)
+                    .on_press(Message::SongControl(SongControl::SeekTo(bookmark.position)))
+                    .style(theme::Button::Custom(Box::new(ButtonType::Text.default()))),
+                    button_icon(
+                        "close",
+                        14.,
+                        Message::Bookmark(BookmarkControl::Remove(index)),
+                        ButtonType::Info.default(),
+                    ),
+                )
+                .spacing(5)
+                .align_items(Alignment::Center),
+            );
+        }
+        if self.bookmarks().is_empty() {
+            list = list.push(text("没有书签，播放时按 b 可在当前位置快速添加").size(16));
+        }
+
+        container(column!(add, Scrollable::new(list).width(Length::Fill)).spacing(15))
+            .padding(30)
+            .style(theme::Container::Custom(Box::new(
+                style::ContainerStyle::BackgroundWithAlpha(0.2),
+            )))
+            .into()
+    }
+}
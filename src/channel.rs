@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use kira::clock::clock_info::ClockInfoProvider;
+use kira::effect::{Effect, EffectBuilder};
+use kira::modulator::value_provider::ModulatorValueProvider;
+use kira::Frame;
+
+/// 声道效果器：单声道混音、左右声道互换
+///
+/// 声道平衡由歌曲本身的 panning 完成，但 panning 在效果器之前，
+/// 单声道混音会把平衡抹掉，所以单声道时由这里在混音之后重新平衡
+pub struct ChannelMixBuilder;
+
+#[derive(Clone)]
+pub struct ChannelMixHandle {
+    mono: Arc<AtomicBool>,
+    swap: Arc<AtomicBool>,
+    panning: Arc<AtomicU32>,
+}
+impl ChannelMixHandle {
+    /// 设置单声道混音、声道互换，panning 为单声道时的平衡，0 为最左，1 为最右
+    pub fn set(&mut self, mono: bool, swap: bool, panning: f32) {
+        self.mono.store(mono, Ordering::Relaxed);
+        self.swap.store(swap, Ordering::Relaxed);
+        self.panning.store(panning.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl EffectBuilder for ChannelMixBuilder {
+    type Handle = ChannelMixHandle;
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        let handle = ChannelMixHandle {
+            mono: Arc::new(AtomicBool::new(false)),
+            swap: Arc::new(AtomicBool::new(false)),
+            panning: Arc::new(AtomicU32::new(0.5f32.to_bits())),
+        };
+        (Box::new(ChannelMix(handle.clone())), handle)
+    }
+}
+
+struct ChannelMix(ChannelMixHandle);
+
+impl Effect for ChannelMix {
+    fn process(
+        &mut self,
+        input: Frame,
+        _dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        if self.0.mono.load(Ordering::Relaxed) {
+            let panning = f32::from_bits(self.0.panning.load(Ordering::Relaxed));
+            input.as_mono().panned(panning)
+        } else if self.0.swap.load(Ordering::Relaxed) {
+            Frame::new(input.right, input.left)
+        } else {
+            input
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use encoding_rs::Encoding;
+
+use crate::util;
+
+const MAX_CHAPTERS: usize = 10000; // 章节轨道最多读取的样本数，避免损坏文件中的异常计数
+
+/// 歌曲中的章节，有声书、播客常见
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub title: String,
+    pub start: f32, // 开始时间，单位：s
+}
+
+/// 读取章节：mp3 的 ID3 CHAP 帧，m4a/m4b 的 Nero 章节（moov/udta/chpl）或 QuickTime 章节轨道
+pub fn read(path: &str) -> Vec<Chapter> {
+    let ext = std::path::Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mut chapters = match ext.as_str() {
+        "mp3" => read_id3(path),
+        "m4a" | "m4b" | "mp4" => match read_mp4(path) {
+            Ok(chapters) => chapters,
+            Err(err) => {
+                util::log_err(format!("read mp4 chapters err path={} err={}", path, err));
+                vec![]
+            }
+        },
+        _ => vec![],
+    };
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+    chapters
+}
+
+fn read_id3(path: &str) -> Vec<Chapter> {
+    let tag = match id3::Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(_) => return vec![],
+    };
+    // CHAP 帧不一定按时间先后存放，先按开始时间排序再编号，不依赖 CTOC 中的顺序
+    let mut chapters: Vec<&id3::frame::Chapter> = tag.chapters().collect();
+    chapters.sort_by_key(|chapter| chapter.start_time);
+    chapters
+        .into_iter()
+        .enumerate()
+        .map(|(index, chapter)| {
+            let title = chapter
+                .frames
+                .iter()
+                .find(|frame| frame.id() == "TIT2")
+                .and_then(|frame| frame.content().text())
+                .map(|title| title.to_string())
+                .unwrap_or_else(|| format!("第 {} 章", index + 1));
+            Chapter {
+                title,
+                start: chapter.start_time as f32 / 1000.,
+            }
+        })
+        .collect()
+}
+
+/// 按路径逐层查找 MP4 box，返回最后一层 box 的内容
+fn find_box(file: &mut File, path: &[&[u8; 4]]) -> std::io::Result<Option<Vec<u8>>> {
+    let mut end = file.seek(SeekFrom::End(0))?;
+    let mut pos = 0;
+    let mut depth = 0;
+    while pos + 8 <= end {
+        file.seek(SeekFrom::Start(pos))?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+        let mut size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let mut header_len = 8;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            file.read_exact(&mut large)?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            size = end - pos;
+        }
+        if size < header_len {
+            break;
+        }
+        // 文件截断或损坏时 box 大小可能超出范围，不按它分配内存
+        if size > end - pos {
+            return Ok(None);
+        }
+        if &header[4..8] == path[depth] {
+            if depth + 1 == path.len() {
+                let mut content = vec![0u8; (size - header_len) as usize];
+                file.read_exact(&mut content)?;
+                return Ok(Some(content));
+            }
+            // 进入子 box
+            depth += 1;
+            end = pos + size;
+            pos += header_len;
+            continue;
+        }
+        pos += size;
+    }
+    Ok(None)
+}
+
+/// 读取 MP4 章节：优先使用 Nero 章节，没有时读取 QuickTime 章节轨道
+fn read_mp4(path: &str) -> std::io::Result<Vec<Chapter>> {
+    let mut file = File::open(path)?;
+    let moov = match find_box(&mut file, &[b"moov"])? {
+        Some(moov) => moov,
+        None => return Ok(vec![]),
+    };
+    let chapters = child_box(&moov, &[b"udta", b"chpl"])
+        .map(parse_chpl)
+        .unwrap_or_default();
+    if !chapters.is_empty() {
+        return Ok(chapters);
+    }
+    read_chapter_track(&mut file, &moov)
+}
+
+/// 逐个列出内存中的子 box，返回 (类型, 内容)，大小超出范围时停止
+fn child_boxes(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut boxes = vec![];
+    let mut pos = 0;
+    while let Some(size) = read_u32(data, pos) {
+        let (size, header_len) = match size {
+            0 => (data.len() - pos, 8),
+            1 => match read_u64(data, pos + 8) {
+                Some(size) => (usize::try_from(size).unwrap_or(usize::MAX), 16),
+                None => break,
+            },
+            size => (size as usize, 8),
+        };
+        if size < header_len || size > data.len() - pos {
+            break;
+        }
+        boxes.push((&data[pos + 4..pos + 8], &data[pos + header_len..pos + size]));
+        pos += size;
+    }
+    boxes
+}
+
+/// 按路径逐层查找子 box 的内容
+fn child_box<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter().try_fold(data, |data, kind| {
+        child_boxes(data)
+            .into_iter()
+            .find(|(box_kind, _)| box_kind == kind)
+            .map(|(_, content)| content)
+    })
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], pos: usize) -> Option<u64> {
+    let bytes = data.get(pos..pos.checked_add(8)?)?;
+    Some(u64::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// full box 的 version 为 1 时时间字段为 64 位，按版本选择字段的偏移
+fn version_offset(data: &[u8], v0: usize, v1: usize) -> usize {
+    if data.first() == Some(&1) {
+        v1
+    } else {
+        v0
+    }
+}
+
+/// QuickTime 章节轨道：音频轨道的 tref/chap 指向一条文本轨道，
+/// 文本轨道的每个样本为一章的标题，样本的开始时间即章节的开始时间
+fn read_chapter_track(file: &mut File, moov: &[u8]) -> std::io::Result<Vec<Chapter>> {
+    let traks: Vec<&[u8]> = child_boxes(moov)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+        .map(|(_, content)| content)
+        .collect();
+    let chapter_id = traks
+        .iter()
+        .find_map(|trak| child_box(trak, &[b"tref", b"chap"]))
+        .and_then(|chap| read_u32(chap, 0));
+    let trak = traks.iter().find(|trak| {
+        let id = child_box(trak, &[b"tkhd"])
+            .and_then(|tkhd| read_u32(tkhd, version_offset(tkhd, 12, 20)));
+        chapter_id.is_some() && id == chapter_id
+    });
+    let samples = trak.and_then(|trak| chapter_samples(trak));
+    let (timescale, samples) = match samples {
+        Some(samples) => samples,
+        None => return Ok(vec![]),
+    };
+
+    let file_len = file.seek(SeekFrom::End(0))?;
+    let mut chapters = vec![];
+    for (index, (start, offset, size)) in samples.into_iter().enumerate() {
+        // 样本为 2 字节长度加文本
+        let mut title = String::new();
+        if size >= 2 && offset.saturating_add(2) <= file_len {
+            file.seek(SeekFrom::Start(offset))?;
+            let mut len = [0u8; 2];
+            file.read_exact(&mut len)?;
+            let len = (u16::from_be_bytes(len) as u64)
+                .min(size - 2)
+                .min(file_len - offset - 2);
+            let mut text = vec![0u8; len as usize];
+            file.read_exact(&mut text)?;
+            title = decode_text(&text);
+        }
+        if title.trim().is_empty() {
+            title = format!("第 {} 章", index + 1);
+        }
+        chapters.push(Chapter {
+            title,
+            start: (start as f64 / timescale as f64) as f32,
+        });
+    }
+    Ok(chapters)
+}
+
+/// 章节轨道的样本，(开始时间, 文件偏移, 大小)
+type ChapterSample = (u64, u64, u64);
+
+/// 读取文本轨道的时间刻度及各样本
+fn chapter_samples(trak: &[u8]) -> Option<(u32, Vec<ChapterSample>)> {
+    let mdhd = child_box(trak, &[b"mdia", b"mdhd"])?;
+    let timescale = read_u32(mdhd, version_offset(mdhd, 12, 20)).filter(|scale| *scale > 0)?;
+    let stbl = child_box(trak, &[b"mdia", b"minf", b"stbl"])?;
+
+    // stts：(样本数, 每个样本的时长)
+    let stts = child_box(stbl, &[b"stts"])?;
+    let mut starts = vec![];
+    let mut time = 0u64;
+    for entry in stts
+        .get(8..)?
+        .chunks_exact(8)
+        .take(read_u32(stts, 4)? as usize)
+    {
+        let (count, delta) = (read_u32(entry, 0)?, read_u32(entry, 4)?);
+        for _ in 0..count.min(MAX_CHAPTERS as u32) {
+            if starts.len() >= MAX_CHAPTERS {
+                break;
+            }
+            starts.push(time);
+            time += delta as u64;
+        }
+    }
+
+    // stsz：统一大小或逐个样本的大小
+    let stsz = child_box(stbl, &[b"stsz"])?;
+    let sample_size = read_u32(stsz, 4)?;
+    let sample_count = (read_u32(stsz, 8)? as usize).min(starts.len());
+    let sizes: Vec<u64> = match sample_size {
+        0 => stsz
+            .get(12..)?
+            .chunks_exact(4)
+            .take(sample_count)
+            .filter_map(|entry| read_u32(entry, 0).map(u64::from))
+            .collect(),
+        size => vec![size as u64; sample_count],
+    };
+
+    // stco/co64：各 chunk 的文件偏移
+    let chunk_offsets: Vec<u64> = match child_box(stbl, &[b"stco"]) {
+        Some(stco) => stco
+            .get(8..)?
+            .chunks_exact(4)
+            .filter_map(|entry| read_u32(entry, 0).map(u64::from))
+            .collect(),
+        None => child_box(stbl, &[b"co64"])?
+            .get(8..)?
+            .chunks_exact(8)
+            .filter_map(|entry| read_u64(entry, 0))
+            .collect(),
+    };
+
+    // stsc：(起始 chunk 序号，从 1 开始, 每个 chunk 的样本数, 样本描述序号)
+    let stsc = child_box(stbl, &[b"stsc"])?;
+    let stsc: Vec<(u32, u32)> = stsc
+        .get(8..)?
+        .chunks_exact(12)
+        .filter_map(|entry| Some((read_u32(entry, 0)?, read_u32(entry, 4)?)))
+        .collect();
+
+    let mut samples = vec![];
+    for (chunk, chunk_offset) in chunk_offsets.iter().enumerate() {
+        let chunk = chunk as u32 + 1;
+        let per_chunk = stsc
+            .iter()
+            .rev()
+            .find(|(first, _)| *first <= chunk)
+            .map(|(_, per_chunk)| *per_chunk)
+            .unwrap_or(0);
+        let mut offset = *chunk_offset;
+        for _ in 0..per_chunk {
+            let index = samples.len();
+            if index >= sizes.len() {
+                return Some((timescale, samples));
+            }
+            samples.push((starts[index], offset, sizes[index]));
+            offset = offset.saturating_add(sizes[index]);
+        }
+    }
+    Some((timescale, samples))
+}
+
+/// 章节标题为 UTF-8，带 BOM 时为 UTF-16
+fn decode_text(text: &[u8]) -> String {
+    match Encoding::for_bom(text) {
+        Some((encoding, bom_len)) => encoding
+            .decode_without_bom_handling(&text[bom_len..])
+            .0
+            .into_owned(),
+        None => String::from_utf8_lossy(text).to_string(),
+    }
+}
+
+/// 解析 Nero 章节（chpl box）
+fn parse_chpl(data: &[u8]) -> Vec<Chapter> {
+    // version(1) flags(3) [reserved(4)，version 1] count(1) 之后逐条为 start(8，100ns) len(1) title
+    let mut chapters = vec![];
+    if data.len() < 5 {
+        return chapters;
+    }
+    let mut pos = if data[0] == 1 { 8 } else { 4 };
+    let count = *data.get(pos).unwrap_or(&0);
+    pos += 1;
+    for _ in 0..count {
+        if pos + 9 > data.len() {
+            break;
+        }
+        let mut start = [0u8; 8];
+        start.copy_from_slice(&data[pos..pos + 8]);
+        let start = u64::from_be_bytes(start);
+        let len = data[pos + 8] as usize;
+        pos += 9;
+        let title = match data.get(pos..pos + len) {
+            Some(title) => String::from_utf8_lossy(title).to_string(),
+            None => break,
+        };
+        pos += len;
+        chapters.push(Chapter {
+            title,
+            start: (start as f64 / 10_000_000.) as f32,
+        });
+    }
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut data = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(content);
+        data
+    }
+
+    /// full box 内容：version 0、flags 0 之后接各个 u32 字段
+    fn full_box(kind: &[u8; 4], fields: &[u32]) -> Vec<u8> {
+        let content: Vec<u8> = std::iter::once(0)
+            .chain(fields.iter().copied())
+            .flat_map(u32::to_be_bytes)
+            .collect();
+        mp4_box(kind, &content)
+    }
+
+    fn write_file(name: &str, data: &[u8]) -> String {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, data).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    /// 音频轨道 1 通过 tref/chap 指向文本轨道 2，章节标题放在 mdat 中，udta 附加在 moov 末尾
+    fn chapter_track_file(titles: &[&str], deltas: &[u32], udta: &[u8]) -> Vec<u8> {
+        let ftyp = mp4_box(b"ftyp", b"M4B \0\0\0\0");
+        let samples: Vec<Vec<u8>> = titles
+            .iter()
+            .map(|title| {
+                let mut sample = (title.len() as u16).to_be_bytes().to_vec();
+                sample.extend_from_slice(title.as_bytes());
+                sample
+            })
+            .collect();
+        let mdat = mp4_box(b"mdat", &samples.concat());
+        let first_offset = (ftyp.len() + 8) as u32;
+
+        let tkhd = |id: u32| full_box(b"tkhd", &[0, 0, id, 0, 0]);
+        let audio = mp4_box(
+            b"trak",
+            &[
+                tkhd(1),
+                mp4_box(b"tref", &mp4_box(b"chap", &2u32.to_be_bytes())),
+            ]
+            .concat(),
+        );
+        let stts: Vec<u32> = std::iter::once(deltas.len() as u32)
+            .chain(deltas.iter().flat_map(|delta| [1, *delta]))
+            .collect();
+        let stsz: Vec<u32> = [0, samples.len() as u32]
+            .into_iter()
+            .chain(samples.iter().map(|sample| sample.len() as u32))
+            .collect();
+        // 全部样本放在一个 chunk 中
+        let stbl = [
+            full_box(b"stts", &stts),
+            full_box(b"stsz", &stsz),
+            full_box(b"stsc", &[1, 1, samples.len() as u32, 1]),
+            full_box(b"stco", &[1, first_offset]),
+        ]
+        .concat();
+        let mdia = [
+            full_box(b"mdhd", &[0, 0, 1000, 0, 0]),
+            mp4_box(b"minf", &mp4_box(b"stbl", &stbl)),
+        ]
+        .concat();
+        let text = mp4_box(b"trak", &[tkhd(2), mp4_box(b"mdia", &mdia)].concat());
+        let moov = mp4_box(b"moov", &[audio, text, udta.to_vec()].concat());
+        [ftyp, mdat, moov].concat()
+    }
+
+    #[test]
+    fn reads_quicktime_chapter_track() {
+        let data = chapter_track_file(&["开始", "", "Chapter 3"], &[90_000, 60_500, 30_000], &[]);
+        let path = write_file("silk_chapter_track.m4b", &data);
+        let chapters = read(&path);
+        let titles: Vec<&str> = chapters
+            .iter()
+            .map(|chapter| chapter.title.as_str())
+            .collect();
+        assert_eq!(titles, ["开始", "第 2 章", "Chapter 3"]);
+        let starts: Vec<f32> = chapters.iter().map(|chapter| chapter.start).collect();
+        assert_eq!(starts, [0., 90., 150.5]);
+    }
+
+    #[test]
+    fn nero_chapters_take_priority() {
+        let mut chpl = vec![0, 0, 0, 0, 1];
+        chpl.extend_from_slice(&50_000_000u64.to_be_bytes());
+        chpl.push(4);
+        chpl.extend_from_slice(b"Nero");
+        let udta = mp4_box(b"udta", &mp4_box(b"chpl", &chpl));
+        let data = chapter_track_file(&["QT"], &[1000], &udta);
+        let path = write_file("silk_chapter_nero.m4b", &data);
+        assert_eq!(
+            read(&path),
+            [Chapter {
+                title: "Nero".to_string(),
+                start: 5.
+            }]
+        );
+    }
+
+    #[test]
+    fn oversized_box_is_ignored() {
+        // moov 声称的大小远超文件长度
+        let mut data = mp4_box(b"ftyp", b"M4B \0\0\0\0");
+        data.extend_from_slice(&u32::MAX.to_be_bytes());
+        data.extend_from_slice(b"moov");
+        data.extend_from_slice(&[0; 16]);
+        let path = write_file("silk_chapter_truncated.m4b", &data);
+        assert!(read(&path).is_empty());
+
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(find_box(&mut file, &[b"moov"]).unwrap(), None);
+    }
+}
@@ -45,3 +45,18 @@ where
         .on_press(msg)
         .style(theme::Button::Custom(Box::new(style)))
 }
+
+/// create a button with text
+pub fn button_text<'a, Message>(
+    label: &str,
+    size: f32,
+    msg: Message,
+    style: ButtonStyle,
+) -> button::Button<'a, Message>
+where
+    Message: Clone + 'a,
+{
+    button(text(label.to_string()).size(size))
+        .on_press(msg)
+        .style(theme::Button::Custom(Box::new(style)))
+}
@@ -2,10 +2,24 @@ use rfd::FileDialog;
 use serde::{Deserialize, Serialize};
 
 use iced::{
-    widget::{button, checkbox, column, pick_list, radio, row, text, text_input, Scrollable}, window, Alignment, Command, Length, Theme
+    widget::{button, checkbox, column, pick_list, radio, row, slider, text, text_input, Scrollable}, window, Alignment, Command, Length, Theme
 };
 
-use crate::{util, Message, PlayMode, SilkPlayer, View};
+use crate::{
+    audio,
+    equalizer::{ChangeEq, EqConfig},
+    lyric::LyricPriority,
+    replaygain::{ReplayGainConfig, ReplayGainMode},
+    sleep::{SleepControl, SleepMode},
+    util,
+    visualizer::VisualStyle,
+    Message, PlayMode, Repeat, SilkPlayer, View,
+};
+
+const MAX_CROSSFADE: f32 = 12.0; // 交叉淡入淡出最长时间，单位：s
+const MAX_SLEEP_FADE: f32 = 120.0; // 定时关闭淡出最长时间，单位：s
+const MAX_CLICK_FADE: u32 = 1000; // 暂停、跳转淡入淡出最长时间，单位：ms
+const DEFAULT_DEVICE: &str = "系统默认";
 
 #[derive(Debug, Clone)]
 pub enum ConfigMessage {
@@ -14,10 +28,34 @@ pub enum ConfigMessage {
     SubmitMonitor,
     ChangeTheme(ThemeConfig),
     ChangePlayMode,
+    ChangeRepeat,
     ChangeVolume(f32),
     ChangeDesktopLyric(ChangeDesktopLyric),
+    SelectLyricDir,
+    ChangeLyricDir(String),
+    SubmitLyricDir,
+    ChangeLyricPriority(LyricPriority),
     ChangeWinMode(bool),
     ChangeAutoPlay(bool),
+    ChangeCrossfade(String),
+    ChangeManualCrossfade(String),
+    ChangeReplayGain(ChangeReplayGain),
+    LoudnessScan(LoudnessScanControl),
+    ChangeEq(ChangeEq),
+    ChangeSpeed(f32),
+    ChangeKeepPitch(bool),
+    ChangeRememberSpeed(bool),
+    ChangeSleepFade(String),
+    ChangeClickFade(String),
+    ChangeBalance(f32),
+    ChangeVisualizer(VisualStyle),
+    ChangeMono(bool),
+    ChangeSwapChannels(bool),
+    ChangeResumeMinutes(String),
+    AddResumeFolder,
+    RemoveResumeFolder(String),
+    ChangeOutputDevice(String),
+    RefreshOutputDevices,
 }
 impl ConfigMessage {
     pub fn change(&self, app: &mut SilkPlayer) -> Command<Message> {
@@ -43,6 +81,15 @@ impl ConfigMessage {
             ConfigMessage::ChangePlayMode => {
                 app.setting.play_mode = app.setting.play_mode.next();
                 app.setting.save();
+                app.reset_next_song();
+                if app.setting.play_mode.is_shuffle() {
+                    app.rebuild_shuffle();
+                }
+            }
+            ConfigMessage::ChangeRepeat => {
+                app.setting.repeat = app.setting.repeat.next();
+                app.setting.save();
+                app.reset_next_song();
             }
             ConfigMessage::ChangeVolume(value) => {
                 app.audio.set_volume(*value);
@@ -80,6 +127,174 @@ impl ConfigMessage {
                 app.setting.auto_play = *auto_play;
                 app.setting.save();
             }
+            ConfigMessage::ChangeCrossfade(value) => {
+                if let Ok(num) = value.parse::<f32>() {
+                    if (0.0..=MAX_CROSSFADE).contains(&num) {
+                        app.setting.crossfade = num;
+                        app.setting.save();
+                        app.reset_next_song();
+                    }
+                }
+            }
+            ConfigMessage::ChangeManualCrossfade(value) => {
+                if let Ok(num) = value.parse::<f32>() {
+                    if (0.0..=MAX_CROSSFADE).contains(&num) {
+                        app.setting.crossfade_manual = num;
+                        app.setting.save();
+                    }
+                }
+            }
+            ConfigMessage::ChangeReplayGain(change) => {
+                match change {
+                    ChangeReplayGain::Mode(mode) => app.setting.replay_gain.mode = *mode,
+                    ChangeReplayGain::Preamp(value) => {
+                        if let Ok(num) = value.parse::<f32>() {
+                            if (-15.0..=15.0).contains(&num) {
+                                app.setting.replay_gain.preamp = num;
+                            }
+                        }
+                    }
+                    ChangeReplayGain::PreventClip(value) => {
+                        app.setting.replay_gain.prevent_clip = *value
+                    }
+                }
+                app.setting.save();
+                app.audio.set_replay_gain(app.setting.replay_gain);
+            }
+            ConfigMessage::LoudnessScan(control) => match control {
+                LoudnessScanControl::Start => {
+                    let paths = match app.music_list.all_list.lock() {
+                        Ok(all_list) => all_list.iter().map(|item| item.path.to_string()).collect(),
+                        Err(_) => vec![],
+                    };
+                    app.loudness_scan.start(
+                        &app.analysis_pool,
+                        paths,
+                        app.setting.loudness_write_tags,
+                    );
+                }
+                LoudnessScanControl::Pause => app.loudness_scan.pause(),
+                LoudnessScanControl::Resume => app.loudness_scan.resume(),
+                LoudnessScanControl::Stop => app.loudness_scan.stop(),
+                LoudnessScanControl::WriteTags(value) => {
+                    app.setting.loudness_write_tags = *value;
+                    app.setting.save();
+                }
+            },
+            ConfigMessage::ChangeSpeed(speed) => app.change_speed(*speed),
+            ConfigMessage::ChangeKeepPitch(value) => {
+                app.setting.keep_pitch = *value;
+                app.setting.save();
+                let speed = app.audio.speed();
+                app.audio.set_speed(speed, *value);
+                app.reset_next_song();
+            }
+            ConfigMessage::ChangeRememberSpeed(value) => {
+                app.setting.remember_speed = *value;
+                app.setting.save();
+            }
+            ConfigMessage::ChangeOutputDevice(name) => {
+                app.setting.output_device = if name == DEFAULT_DEVICE {
+                    String::new()
+                } else {
+                    name.to_string()
+                };
+                app.setting.save();
+                app.audio.set_output_device(&app.setting.output_device);
+                app.reset_next_song();
+            }
+            ConfigMessage::RefreshOutputDevices => {
+                app.app_control.output_devices = audio::output_devices();
+            }
+            ConfigMessage::ChangeSleepFade(value) => {
+                if let Ok(num) = value.parse::<f32>() {
+                    if (0.0..=MAX_SLEEP_FADE).contains(&num) {
+                        app.setting.sleep_fade = num;
+                        app.setting.save();
+                    }
+                }
+            }
+            ConfigMessage::ChangeClickFade(value) => {
+                if let Ok(num) = value.parse::<u32>() {
+                    if num <= MAX_CLICK_FADE {
+                        app.setting.click_fade = num;
+                        app.setting.save();
+                        app.audio.set_click_fade(num as f32 / 1000.);
+                    }
+                }
+            }
+            ConfigMessage::ChangeVisualizer(value) => {
+                app.setting.visualizer = *value;
+                app.setting.save();
+                app.audio
+                    .spectrum()
+                    .set_enabled(*value != VisualStyle::Off);
+            }
+            ConfigMessage::ChangeBalance(value) => {
+                app.setting.balance = *value;
+                app.setting.save();
+                app.apply_channels();
+            }
+            ConfigMessage::ChangeMono(value) => {
+                app.setting.mono = *value;
+                app.setting.save();
+                app.apply_channels();
+            }
+            ConfigMessage::ChangeSwapChannels(value) => {
+                app.setting.swap_channels = *value;
+                app.setting.save();
+                app.apply_channels();
+            }
+            ConfigMessage::SelectLyricDir => {
+                if let Some(folder) = FileDialog::new().pick_folder() {
+                    app.setting.lyric_dir = folder.to_string_lossy().to_string();
+                    app.setting.save();
+                    app.load_lyrics();
+                }
+            }
+            ConfigMessage::ChangeLyricDir(value) => {
+                app.setting.lyric_dir = value.to_string();
+                app.setting.save();
+            }
+            ConfigMessage::SubmitLyricDir => app.load_lyrics(),
+            ConfigMessage::ChangeLyricPriority(value) => {
+                app.setting.lyric_priority = *value;
+                app.setting.save();
+                app.load_lyrics();
+            }
+            ConfigMessage::ChangeResumeMinutes(value) => {
+                if value.is_empty() {
+                    app.setting.resume_minutes = 0.;
+                    app.setting.save();
+                } else if let Ok(num) = value.parse::<f32>() {
+                    if num >= 0. {
+                        app.setting.resume_minutes = num;
+                        app.setting.save();
+                    }
+                }
+            }
+            ConfigMessage::AddResumeFolder => {
+                if let Some(folder) = FileDialog::new().pick_folder() {
+                    let folder = folder.to_string_lossy().to_string();
+                    if !app.setting.resume_folders.contains(&folder) {
+                        app.setting.resume_folders.push(folder);
+                        app.setting.save();
+                    }
+                }
+            }
+            ConfigMessage::RemoveResumeFolder(folder) => {
+                app.setting.resume_folders.retain(|item| item != folder);
+                app.setting.save();
+            }
+            ConfigMessage::ChangeEq(change) => {
+                if let ChangeEq::PresetName(name) = change {
+                    app.app_control.eq_preset_name = name.to_string();
+                    return Command::none();
+                }
+                app.setting.eq.change(change, &app.app_control.eq_preset_name);
+                app.setting.save();
+                app.apply_eq();
+            }
         }
         Command::none()
     }
@@ -92,15 +307,81 @@ pub enum ChangeDesktopLyric {
     Ahead(String),
 }
 
+#[derive(Debug, Clone)]
+pub enum ChangeReplayGain {
+    Mode(ReplayGainMode),
+    Preamp(String),
+    PreventClip(bool),
+}
+
+#[derive(Debug, Clone)]
+pub enum LoudnessScanControl {
+    Start,
+    Pause,
+    Resume,
+    Stop,
+    WriteTags(bool),
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Setting {
     pub monitor: String,
     pub auto_play: bool,
     pub theme: Option<ThemeConfig>,
     pub play_mode: PlayMode, // 播放模式
+    #[serde(default)]
+    pub repeat: Repeat, // 循环方式
     pub volume: f32,
     pub desktop_lyric: DesktopLyric,
     pub windows: Windows,
+    #[serde(default)]
+    pub crossfade: f32, // 自动切歌交叉淡入淡出时长，单位：s
+    #[serde(default)]
+    pub crossfade_manual: f32, // 手动切歌交叉淡入淡出时长，单位：s
+    #[serde(default)]
+    pub replay_gain: ReplayGainConfig, // 响度均衡
+    #[serde(default)]
+    pub loudness_write_tags: bool, // 响度分析后写回 ReplayGain 标签
+    #[serde(default)]
+    pub eq: EqConfig, // 均衡器
+    #[serde(default)]
+    pub keep_pitch: bool, // 变速时保持音高
+    #[serde(default)]
+    pub remember_speed: bool, // 按歌曲记忆播放速度
+    #[serde(default = "default_sleep_fade")]
+    pub sleep_fade: f32, // 定时关闭前的淡出时长，单位：s
+    #[serde(default)]
+    pub output_device: String, // 输出设备名称，为空时使用系统默认设备
+    #[serde(default = "default_click_fade")]
+    pub click_fade: u32, // 暂停、继续、停止和跳转时的淡入淡出时长，单位：ms
+    #[serde(default)]
+    pub visualizer: VisualStyle, // 播放详情页的频谱样式，低配机器可关闭
+    #[serde(default)]
+    pub balance: f32, // 声道平衡，-1 为最左，1 为最右
+    #[serde(default)]
+    pub mono: bool, // 单声道混音
+    #[serde(default)]
+    pub swap_channels: bool, // 左右声道互换
+    #[serde(default = "default_resume_minutes")]
+    pub resume_minutes: f32, // 时长超过多少分钟的歌曲记录续播位置，0 为不按时长
+    #[serde(default)]
+    pub resume_folders: Vec<String>, // 总是记录续播位置的目录
+    #[serde(default)]
+    pub lyric_dir: String, // 集中存放 .lrc 歌词的目录，按歌手和标题匹配
+    #[serde(default)]
+    pub lyric_priority: LyricPriority, // 内嵌歌词与外部 .lrc 歌词的优先级
+}
+
+fn default_sleep_fade() -> f32 {
+    10.0
+}
+
+fn default_click_fade() -> u32 {
+    150
+}
+
+fn default_resume_minutes() -> f32 {
+    20.0
 }
 
 #[derive(Deserialize, Serialize)]
@@ -136,6 +417,7 @@ impl Setting {
             // monitor: "D:/Jachin/我的文件/音乐/华语".to_string(),
             theme: Some(ThemeConfig::Dark),
             play_mode: Default::default(),
+            repeat: Default::default(),
             volume: 1.0,
             desktop_lyric: DesktopLyric {
                 line: ConfigDesktopLyricLine::Two,
@@ -153,6 +435,24 @@ impl Setting {
                 height: 697.0,
             },
             auto_play: true,
+            crossfade: 0.0,
+            crossfade_manual: 0.0,
+            replay_gain: Default::default(),
+            loudness_write_tags: false,
+            eq: Default::default(),
+            keep_pitch: false,
+            remember_speed: false,
+            sleep_fade: default_sleep_fade(),
+            click_fade: default_click_fade(),
+            visualizer: VisualStyle::default(),
+            balance: 0.,
+            mono: false,
+            swap_channels: false,
+            resume_minutes: default_resume_minutes(),
+            resume_folders: vec![],
+            lyric_dir: String::new(),
+            lyric_priority: LyricPriority::default(),
+            output_device: String::new(),
         }
     }
 
@@ -336,7 +636,250 @@ impl SilkPlayer {
         let auto_play = checkbox("启动时恢复播放", self.setting.auto_play)
             .on_toggle(|v| Message::ChangeConfig(ConfigMessage::ChangeAutoPlay(v)));
 
-        let general = column!("常规设置", monitor, theme, win_mode, auto_play).spacing(5);
+        let visualizer = row!(
+            "频谱",
+            pick_list(&VisualStyle::ALL[..], Some(self.setting.visualizer), |value| {
+                Message::ChangeConfig(ConfigMessage::ChangeVisualizer(value))
+            }),
+            text("播放详情页显示，配置较低时可关闭"),
+        )
+        .spacing(gap)
+        .align_items(Alignment::Center);
+
+        let general =
+            column!("常规设置", monitor, theme, visualizer, win_mode, auto_play).spacing(5);
+
+        let mut devices = vec![DEFAULT_DEVICE.to_string()];
+        devices.extend(self.app_control.output_devices.iter().cloned());
+        let selected_device = if self.setting.output_device.is_empty() {
+            DEFAULT_DEVICE.to_string()
+        } else {
+            self.setting.output_device.to_string()
+        };
+        let current_device = match self.audio.device() {
+            Some(name) => format!("当前使用：{}", name),
+            None => format!("当前使用：{}", DEFAULT_DEVICE),
+        };
+        let output_device = row!(
+            pick_list(devices, Some(selected_device), |value| {
+                Message::ChangeConfig(ConfigMessage::ChangeOutputDevice(value))
+            }),
+            button("刷新").on_press(Message::ChangeConfig(ConfigMessage::RefreshOutputDevices)),
+            text(current_device),
+        )
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let audio = column!(
+            "",
+            "音频设置",
+            form_item("输出设备", output_device.into()),
+            form_item(
+                "交叉淡入淡出",
+                text_input("0~12 秒", &self.setting.crossfade.to_string())
+                    .on_input(|value| Message::ChangeConfig(ConfigMessage::ChangeCrossfade(value)))
+                    .width(150)
+                    .into(),
+            ),
+            form_item(
+                "手动切歌淡入淡出",
+                text_input("0~12 秒", &self.setting.crossfade_manual.to_string())
+                    .on_input(|value| {
+                        Message::ChangeConfig(ConfigMessage::ChangeManualCrossfade(value))
+                    })
+                    .width(150)
+                    .into(),
+            ),
+            form_item(
+                "暂停/跳转淡入淡出",
+                text_input("0~1000 毫秒", &self.setting.click_fade.to_string())
+                    .on_input(|value| Message::ChangeConfig(ConfigMessage::ChangeClickFade(value)))
+                    .width(150)
+                    .into(),
+            ),
+            form_item(
+                "响度均衡",
+                pick_list(
+                    &ReplayGainMode::ALL[..],
+                    Some(self.setting.replay_gain.mode),
+                    |value| {
+                        Message::ChangeConfig(ConfigMessage::ChangeReplayGain(
+                            ChangeReplayGain::Mode(value),
+                        ))
+                    },
+                )
+                .into(),
+            ),
+            form_item(
+                "前置增益(dB)",
+                text_input("", &self.setting.replay_gain.preamp.to_string())
+                    .on_input(|value| {
+                        Message::ChangeConfig(ConfigMessage::ChangeReplayGain(
+                            ChangeReplayGain::Preamp(value),
+                        ))
+                    })
+                    .width(150)
+                    .into(),
+            ),
+            checkbox("根据峰值防止削波", self.setting.replay_gain.prevent_clip).on_toggle(|v| {
+                Message::ChangeConfig(ConfigMessage::ChangeReplayGain(
+                    ChangeReplayGain::PreventClip(v),
+                ))
+            }),
+            form_item(
+                "声道平衡",
+                row!(
+                    "左",
+                    slider(-1.0..=1.0, self.setting.balance, |v| {
+                        Message::ChangeConfig(ConfigMessage::ChangeBalance(v))
+                    })
+                    .step(0.05)
+                    .width(200),
+                    "右",
+                    text(balance_label(self.setting.balance)),
+                    button("居中")
+                        .on_press(Message::ChangeConfig(ConfigMessage::ChangeBalance(0.))),
+                )
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .into(),
+            ),
+            checkbox("单声道", self.setting.mono)
+                .on_toggle(|v| Message::ChangeConfig(ConfigMessage::ChangeMono(v))),
+            checkbox("左右声道互换", self.setting.swap_channels)
+                .on_toggle(|v| Message::ChangeConfig(ConfigMessage::ChangeSwapChannels(v))),
+            checkbox("变速时保持音高", self.setting.keep_pitch)
+                .on_toggle(|v| Message::ChangeConfig(ConfigMessage::ChangeKeepPitch(v))),
+            checkbox("按歌曲记忆播放速度", self.setting.remember_speed)
+                .on_toggle(|v| Message::ChangeConfig(ConfigMessage::ChangeRememberSpeed(v))),
+            form_item(
+                "长音频续播",
+                row!(
+                    "时长超过",
+                    text_input("分钟", &self.setting.resume_minutes.to_string())
+                        .on_input(|value| {
+                            Message::ChangeConfig(ConfigMessage::ChangeResumeMinutes(value))
+                        })
+                        .width(80),
+                    "分钟，或位于以下目录",
+                    button("添加目录")
+                        .on_press(Message::ChangeConfig(ConfigMessage::AddResumeFolder)),
+                )
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .into(),
+            ),
+            self.resume_folders_view(),
+            form_item("响度分析", self.loudness_scan_view()),
+            checkbox("分析后写入 ReplayGain 标签", self.setting.loudness_write_tags).on_toggle(|v| {
+                Message::ChangeConfig(ConfigMessage::LoudnessScan(LoudnessScanControl::WriteTags(v)))
+            }),
+        )
+        .spacing(5);
+
+        let sleep = column!(
+            "",
+            "定时关闭",
+            form_item("当前", self.sleep_timer_view()),
+            form_item(
+                "倒计时",
+                row!(
+                    sleep_button("15 分钟", SleepMode::Minutes(15)),
+                    sleep_button("30 分钟", SleepMode::Minutes(30)),
+                    sleep_button("60 分钟", SleepMode::Minutes(60)),
+                    text_input("分钟", &self.sleep_timer.minutes_input)
+                        .on_input(|value| Message::SleepTimer(SleepControl::Minutes(value)))
+                        .width(80),
+                    button("开始").on_press_maybe(
+                        self.sleep_timer
+                            .minutes_input
+                            .trim()
+                            .parse::<u32>()
+                            .ok()
+                            .map(|minutes| {
+                                Message::SleepTimer(SleepControl::Start(SleepMode::Minutes(
+                                    minutes,
+                                )))
+                            }),
+                    ),
+                )
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .into(),
+            ),
+            form_item(
+                "指定时刻",
+                row!(
+                    text_input("HH:MM", &self.sleep_timer.at_input)
+                        .on_input(|value| Message::SleepTimer(SleepControl::At(value)))
+                        .width(80),
+                    button("开始").on_press(Message::SleepTimer(SleepControl::Start(
+                        SleepMode::At(self.sleep_timer.at_input.to_string())
+                    ))),
+                )
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .into(),
+            ),
+            form_item(
+                "按歌曲",
+                row!(
+                    sleep_button("播完当前歌曲", SleepMode::AfterCurrent),
+                    text_input("首数", &self.sleep_timer.tracks_input)
+                        .on_input(|value| Message::SleepTimer(SleepControl::Tracks(value)))
+                        .width(80),
+                    button("开始").on_press_maybe(
+                        self.sleep_timer
+                            .tracks_input
+                            .trim()
+                            .parse::<u32>()
+                            .ok()
+                            .map(|tracks| {
+                                Message::SleepTimer(SleepControl::Start(SleepMode::AfterTracks(
+                                    tracks,
+                                )))
+                            }),
+                    ),
+                )
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .into(),
+            ),
+            form_item(
+                "淡出时长",
+                text_input("0~120 秒", &self.setting.sleep_fade.to_string())
+                    .on_input(|value| Message::ChangeConfig(ConfigMessage::ChangeSleepFade(value)))
+                    .width(150)
+                    .into(),
+            ),
+        )
+        .spacing(5);
+
+        let lyric = column!(
+            "",
+            "歌词",
+            form_item(
+                "歌词来源",
+                pick_list(&LyricPriority::ALL[..], Some(self.setting.lyric_priority), |value| {
+                    Message::ChangeConfig(ConfigMessage::ChangeLyricPriority(value))
+                })
+                .into(),
+            ),
+            form_item(
+                "歌词目录",
+                row!(
+                    text_input("歌曲同目录下的同名 .lrc 总会查找", &self.setting.lyric_dir)
+                        .on_input(|value| Message::ChangeConfig(ConfigMessage::ChangeLyricDir(value)))
+                        .on_submit(Message::ChangeConfig(ConfigMessage::SubmitLyricDir))
+                        .width(500),
+                    button("选择").on_press(Message::ChangeConfig(ConfigMessage::SelectLyricDir)),
+                )
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .into(),
+            ),
+        )
+        .spacing(5);
 
         let desktop_lyric = column!(
             "",
@@ -404,18 +947,27 @@ impl SilkPlayer {
             form_item("l ------ ", text("进入播放列表页").into()),
             form_item("space ------ ", text("播放/暂停").into()),
             form_item("Ctrl+up/down ------ ", text("音量增加/减小").into()),
-            form_item("Ctrl+left/right ------ ", text("上一首/下一首").into()),
+            form_item("Ctrl+left/right ------ ", text("上一首/下一首，有章节时为上一章/下一章").into()),
+            form_item("[ / ] ------ ", text("减慢/加快播放速度").into()),
+            form_item("\\ ------ ", text("恢复原速").into()),
+            form_item(", ------ ", text("设置 A-B 循环的 A 点").into()),
+            form_item(". ------ ", text("设置 A-B 循环的 B 点").into()),
+            form_item("/ ------ ", text("清除 A-B 循环").into()),
+            form_item("; ------ ", text("保存 A-B 循环区间到歌曲（无区间时删除）").into()),
+            form_item("t ------ ", text("定时关闭 15/30/60/90 分钟/取消").into()),
+            form_item("b ------ ", text("在当前位置添加书签").into()),
             "",
             "全局快捷键",
             form_item("Ctrl+num8/num2 ------ ", text("音量增加/减小").into()),
-            form_item("Ctrl+num4/num6 ------ ", text("上一首/下一首").into()),
+            form_item("Ctrl+num4/num6 ------ ", text("上一首/下一首，有章节时为上一章/下一章").into()),
             form_item("Ctrl+num5 ------ ", text("暂停/播放").into()),
+            form_item("Ctrl+num0 ------ ", text("取消定时关闭").into()),
         )
         .spacing(5);
 
         // column!("常规设置", monitor, theme, wim_mode, desktop_lyric)
         Scrollable::new(
-            column!(general, desktop_lyric, key)
+            column!(general, audio, self.eq_view(), sleep, lyric, desktop_lyric, key)
                 .padding([10, 50])
                 .spacing(gap),
         )
@@ -424,6 +976,83 @@ impl SilkPlayer {
     }
 }
 
+impl SilkPlayer {
+    /// 按设置应用声道平衡、单声道和声道互换
+    pub fn apply_channels(&mut self) {
+        let setting = &self.setting;
+        self.audio
+            .set_channels(setting.balance, setting.mono, setting.swap_channels);
+    }
+
+    fn loudness_scan_view(&self) -> View<'_> {
+        let scan = &self.loudness_scan;
+        let control = |label, control| {
+            button(label).on_press(Message::ChangeConfig(ConfigMessage::LoudnessScan(control)))
+        };
+        if !scan.is_running() {
+            return row!(
+                control("分析曲库", LoudnessScanControl::Start),
+                text("为没有 ReplayGain 标签的歌曲计算响度"),
+            )
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .into();
+        }
+
+        let (done, total) = scan.progress();
+        let toggle = if scan.is_paused() {
+            control("继续", LoudnessScanControl::Resume)
+        } else {
+            control("暂停", LoudnessScanControl::Pause)
+        };
+        row!(
+            toggle,
+            control("停止", LoudnessScanControl::Stop),
+            text(format!("已分析 {}/{}", done, total)),
+        )
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into()
+    }
+}
+
+/// 声道平衡的描述，如 左 30%、居中
+fn balance_label(balance: f32) -> String {
+    let percent = (balance.abs() * 100.).round();
+    if percent == 0. {
+        "居中".to_string()
+    } else if balance < 0. {
+        format!("左 {}%", percent)
+    } else {
+        format!("右 {}%", percent)
+    }
+}
+
+fn sleep_button<'a>(label: &'a str, mode: SleepMode) -> View<'a> {
+    button(label)
+        .on_press(Message::SleepTimer(SleepControl::Start(mode)))
+        .into()
+}
+
+impl SilkPlayer {
+    fn resume_folders_view(&self) -> View<'_> {
+        let mut folders = column!().spacing(5);
+        for folder in self.setting.resume_folders.iter() {
+            folders = folders.push(
+                row!(
+                    text(folder),
+                    button("移除").on_press(Message::ChangeConfig(
+                        ConfigMessage::RemoveResumeFolder(folder.to_string())
+                    )),
+                )
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+        folders.into()
+    }
+}
+
 fn form_item<'a>(label: &'a str, content: View<'a>) -> View<'a> {
     row!(label, content)
         .spacing(10)
@@ -453,12 +1082,27 @@ fn load_config() -> Setting {
                     util::log_err(format!("parse config data error {};data={}", err, &data));
                     Setting::new()
                 }
-                Ok(data) => data,
+                Ok(mut setting) => {
+                    migrate_play_mode(&mut setting, &data);
+                    setting
+                }
             }
         }
     }
 }
 
+/// 旧版的单曲循环（Single）是一种播放模式，读取后改为顺序播放加单曲循环；
+/// 列表循环（Cycle）对应默认的 Repeat::All，无需处理
+fn migrate_play_mode(setting: &mut Setting, data: &str) {
+    let old_mode = serde_json::from_str::<serde_json::Value>(data)
+        .ok()
+        .and_then(|value| value.get("play_mode")?.as_str().map(str::to_string));
+    if old_mode.as_deref() == Some("Single") {
+        util::log("migrate play mode Single to Sequence with Repeat::One");
+        setting.repeat = Repeat::One;
+    }
+}
+
 fn save_config(data: String) {
     let data_dir = &util::data_dir();
     if !util::file_exist(&data_dir) {
@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
-use crate::{util, MusicInfo, SilkPlayer};
+use crate::{
+    bookmark::Bookmark, engine::PlayEngine, shuffle::ShuffleOrder, util, MusicInfo, SilkPlayer,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct PlayStatus {
@@ -9,6 +13,10 @@ pub struct PlayStatus {
     current_path: String,
     time: f32,
     is_play: bool,
+    #[serde(default)]
+    shuffle: ShuffleOrder, // 随机播放顺序
+    #[serde(default)]
+    up_next: Vec<String>, // 待播队列
 }
 impl PlayStatus {
     pub fn init(&self, app: &mut SilkPlayer) {
@@ -31,6 +39,13 @@ impl PlayStatus {
 
         app.play_list.init_list(play_list);
         app.app_control.history_list = self.history_path_list.to_vec();
+        app.app_control.shuffle = self.shuffle.clone();
+        app.app_control.up_next = self
+            .up_next
+            .iter()
+            .map(|path| MusicInfo::new(path))
+            .filter(|music_info| !music_info.path.is_empty())
+            .collect();
         app.current_song = current_song;
         if app.setting.auto_play && self.is_play {
             util::log(format!(
@@ -41,6 +56,7 @@ impl PlayStatus {
         } else {
             app.audio.start_play(&app.current_song.path, false);
             app.current_song.time = app.audio.duration();
+            app.load_lyrics();
         }
         app.audio.seek(self.time);
     }
@@ -51,12 +67,14 @@ impl PlayStatus {
             current_path: Default::default(),
             time: Default::default(),
             is_play: Default::default(),
+            shuffle: Default::default(),
+            up_next: Default::default(),
         }
     }
     pub fn load() -> Self {
         load_data()
     }
-    pub fn save(play_list: &Vec<MusicInfo>, history_list: &Vec<String>, current_song: &MusicInfo, is_play: bool, time: f32) {
+    pub fn save(play_list: &Vec<MusicInfo>, history_list: &Vec<String>, shuffle: &ShuffleOrder, up_next: &[MusicInfo], current_song: &MusicInfo, is_play: bool, time: f32) {
         let mut play_path_list = Vec::with_capacity(play_list.len());
         for item in play_list {
             play_path_list.push(item.path.to_string());
@@ -68,20 +86,24 @@ impl PlayStatus {
             current_path: current_song.path.to_string(),
             time,
             is_play,
+            shuffle: shuffle.clone(),
+            up_next: up_next.iter().map(|item| item.path.to_string()).collect(),
         };
         match serde_json::to_string(&play_status) {
             Err(err) => util::log_err(format!("save data error {}", err)),
-            Ok(data) => save_data(data),
+            Ok(data) => save_data(DATA_PATH, data),
         }
     }
 }
 
-impl SilkPlayer {
+impl<E: PlayEngine> SilkPlayer<E> {
     pub fn save_play_status(&self) {
         if let Ok(all_list) = self.play_list.all_list.try_lock() {
             PlayStatus::save(
                 &all_list,
                 &self.app_control.history_list,
+                &self.app_control.shuffle,
+                &self.app_control.up_next,
                 &self.current_song,
                 self.audio.is_play(),
                 self.audio.position(),
@@ -91,7 +113,81 @@ impl SilkPlayer {
 }
 
 
+/// 按歌曲记录的数据
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct TrackRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>, // 播放速度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ab_loop: Option<(f32, f32)>, // A-B 循环区间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume: Option<f32>, // 长音频的续播位置，单位：s
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub finished: bool, // 长音频已播完
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unplayable: Option<String>, // 无法播放的原因，再次成功打开后清除
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<Bookmark>, // 书签，按位置排序
+}
+impl TrackRecord {
+    fn is_empty(&self) -> bool {
+        self.speed.is_none()
+            && self.ab_loop.is_none()
+            && self.resume.is_none()
+            && !self.finished
+            && self.unplayable.is_none()
+            && self.bookmarks.is_empty()
+    }
+}
+
+/// 歌曲数据存储，以文件路径为键
+pub struct TrackStore {
+    records: HashMap<String, TrackRecord>,
+}
+impl Default for TrackStore {
+    fn default() -> Self {
+        Self::load()
+    }
+}
+impl TrackStore {
+    pub fn load() -> Self {
+        let data_path = format!("{}/{}", util::data_dir(), TRACK_PATH);
+        let records = match std::fs::read_to_string(&data_path) {
+            Err(_) => HashMap::new(),
+            Ok(data) => match serde_json::from_str(&data) {
+                Ok(records) => records,
+                Err(err) => {
+                    util::log_err(format!("parse track data error {}", err));
+                    HashMap::new()
+                }
+            },
+        };
+        Self { records }
+    }
+
+    pub fn get(&self, path: &str) -> Option<&TrackRecord> {
+        self.records.get(path)
+    }
+
+    /// 修改歌曲记录并保存，记录为空时删除
+    pub fn update<F: FnOnce(&mut TrackRecord)>(&mut self, path: &str, f: F) {
+        if path.is_empty() {
+            return;
+        }
+        let record = self.records.entry(path.to_string()).or_default();
+        f(record);
+        if record.is_empty() {
+            self.records.remove(path);
+        }
+        match serde_json::to_string(&self.records) {
+            Err(err) => util::log_err(format!("save track data error {}", err)),
+            Ok(data) => save_data(TRACK_PATH, data),
+        }
+    }
+}
+
 const DATA_PATH: &str = "data.json";
+const TRACK_PATH: &str = "track.json";
 /// 加载数据文件
 fn load_data() -> PlayStatus {
     let data_dir = &util::data_dir();
@@ -120,7 +216,7 @@ fn load_data() -> PlayStatus {
     }
 }
 
-fn save_data(data: String) {
+fn save_data(file: &str, data: String) {
     let data_dir = &util::data_dir();
     if !util::file_exist(&data_dir) {
         if let Err(err) = std::fs::create_dir(data_dir) {
@@ -128,7 +224,7 @@ fn save_data(data: String) {
             return;
         }
     }
-    let data_path = format!("{}/{}", data_dir, DATA_PATH);
+    let data_path = format!("{}/{}", data_dir, file);
     if let Err(err) = std::fs::write(&data_path, &data) {
         util::log_err(format!(
             "save data error {};path:{}, data:{}",
@@ -0,0 +1,283 @@
+#[cfg(test)]
+use std::collections::HashMap;
+
+use crate::audio::{Audio, AudioEvent};
+
+/// 播放引擎，播放逻辑所需的基本操作，测试时可用 SimEngine 代替真实音频
+///
+/// 播放进度通过 poll_events 以事件通知：开始、结束、出错和播放位置
+pub trait PlayEngine {
+    /// 打开歌曲，go_play 为 false 时打开后保持暂停，打开失败时返回 false，错误随 Error 事件发出
+    fn load(&mut self, path: &str, go_play: bool) -> bool;
+    /// 切歌时与正在播放的歌曲交叉淡入淡出，fade 为 0 时等同于 load
+    fn cross_play(&mut self, path: &str, fade: f32) -> bool;
+    fn stop(&mut self);
+    fn pause(&mut self);
+    fn toggle_play(&mut self);
+    fn seek(&mut self, pos: f32);
+    /// 当前播放位置，单位：s
+    fn position(&self) -> f32;
+    /// 当前歌曲时长，单位：s
+    fn duration(&self) -> f32;
+    fn is_play(&self) -> bool;
+    fn is_over(&self) -> bool;
+
+    /// 预加载下一首，当前歌曲结束时无缝衔接，随后发出下一首的 Started 事件
+    fn queue_next(&mut self, path: &str, crossfade: f32) -> bool;
+    fn clear_next(&mut self);
+    fn next_path(&self) -> Option<&str>;
+
+    fn loop_region(&self) -> Option<(f32, f32)>;
+    fn set_loop_region(&mut self, region: Option<(f32, f32)>);
+    fn speed(&self) -> f32;
+    fn set_speed(&mut self, speed: f32, keep_pitch: bool);
+    fn fade_out(&mut self, secs: f32);
+    fn restore_volume(&mut self);
+    fn set_equalizer(&mut self, gains: &[f32], preamp: f32);
+
+    /// 取出自上次以来发生的事件，播放到结尾且没有排队的下一首时发出 Ended
+    fn poll_events(&mut self) -> Vec<AudioEvent>;
+}
+
+impl PlayEngine for Audio {
+    fn load(&mut self, path: &str, go_play: bool) -> bool {
+        self.start_play(path, go_play)
+    }
+    fn cross_play(&mut self, path: &str, fade: f32) -> bool {
+        Audio::cross_play(self, path, fade)
+    }
+    fn stop(&mut self) {
+        Audio::stop(self);
+    }
+    fn pause(&mut self) {
+        Audio::pause(self);
+    }
+    fn toggle_play(&mut self) {
+        Audio::toggle_play(self);
+    }
+    fn seek(&mut self, pos: f32) {
+        Audio::seek(self, pos);
+    }
+    fn position(&self) -> f32 {
+        Audio::position(self)
+    }
+    fn duration(&self) -> f32 {
+        Audio::duration(self)
+    }
+    fn is_play(&self) -> bool {
+        Audio::is_play(self)
+    }
+    fn is_over(&self) -> bool {
+        Audio::is_over(self)
+    }
+    fn queue_next(&mut self, path: &str, crossfade: f32) -> bool {
+        Audio::queue_next(self, path, crossfade)
+    }
+    fn clear_next(&mut self) {
+        Audio::clear_next(self);
+    }
+    fn next_path(&self) -> Option<&str> {
+        Audio::next_path(self)
+    }
+    fn loop_region(&self) -> Option<(f32, f32)> {
+        Audio::loop_region(self)
+    }
+    fn set_loop_region(&mut self, region: Option<(f32, f32)>) {
+        Audio::set_loop_region(self, region);
+    }
+    fn speed(&self) -> f32 {
+        Audio::speed(self)
+    }
+    fn set_speed(&mut self, speed: f32, keep_pitch: bool) {
+        Audio::set_speed(self, speed, keep_pitch);
+    }
+    fn fade_out(&mut self, secs: f32) {
+        Audio::fade_out(self, secs);
+    }
+    fn restore_volume(&mut self) {
+        Audio::restore_volume(self);
+    }
+    fn set_equalizer(&mut self, gains: &[f32], preamp: f32) {
+        Audio::set_equalizer(self, gains, preamp);
+    }
+    fn poll_events(&mut self) -> Vec<AudioEvent> {
+        Audio::poll_events(self)
+    }
+}
+
+/// 不依赖音频硬件的模拟引擎，时间只在调用 advance 时走动，结果完全确定
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct SimEngine {
+    durations: HashMap<String, f32>, // 歌曲路径 -> 时长，未登记的歌曲无法打开
+    path: Option<String>,
+    position: f32,
+    playing: bool,
+    ended: bool,
+    next: Option<String>, // 已排队的下一首
+    loop_region: Option<(f32, f32)>,
+    speed: f32,
+    events: Vec<AudioEvent>,
+    pub loaded: Vec<String>, // 依次打开的歌曲，包括无缝衔接的下一首，便于检查播放顺序
+}
+
+#[cfg(test)]
+impl SimEngine {
+    pub fn new(tracks: &[(&str, f32)]) -> Self {
+        Self {
+            durations: tracks
+                .iter()
+                .map(|(path, duration)| (path.to_string(), *duration))
+                .collect(),
+            speed: 1.,
+            ..Default::default()
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn has_events(&self) -> bool {
+        !self.events.is_empty()
+    }
+
+    /// 时间前进 secs 秒，到结尾时切换到已排队的下一首，否则停在结尾
+    pub fn advance(&mut self, mut secs: f32) {
+        while self.playing && secs > 0. {
+            let left = PlayEngine::duration(self) - self.position;
+            if secs < left {
+                self.position += secs;
+                return;
+            }
+            secs -= left;
+            self.position += left;
+            match self.next.take() {
+                Some(next) => self.start(next),
+                None => return,
+            }
+        }
+    }
+
+    fn start(&mut self, path: String) {
+        self.path = Some(path.to_string());
+        self.position = 0.;
+        self.ended = false;
+        self.loop_region = None;
+        self.loaded.push(path.to_string());
+        self.events.push(AudioEvent::Started(path));
+    }
+}
+
+#[cfg(test)]
+impl PlayEngine for SimEngine {
+    fn load(&mut self, path: &str, go_play: bool) -> bool {
+        self.stop();
+        if !self.durations.contains_key(path) {
+            self.events.push(AudioEvent::Error(
+                path.to_string(),
+                "无法打开".to_string(),
+                go_play,
+            ));
+            return false;
+        }
+        self.start(path.to_string());
+        self.playing = go_play;
+        true
+    }
+    fn cross_play(&mut self, path: &str, _fade: f32) -> bool {
+        self.load(path, true)
+    }
+    fn stop(&mut self) {
+        self.clear_next();
+        self.events
+            .retain(|event| !matches!(event, AudioEvent::Started(_)));
+        self.path = None;
+        self.position = 0.;
+        self.playing = false;
+    }
+    fn pause(&mut self) {
+        self.playing = false;
+    }
+    fn toggle_play(&mut self) {
+        if self.playing {
+            self.pause();
+        } else if let Some(ref path) = self.path {
+            // 已播放到结尾时继续播放，发出结束事件切到下一首
+            self.playing = true;
+            if self.is_over() {
+                self.ended = true;
+                self.events.push(AudioEvent::Ended(path.to_string()));
+            }
+        }
+    }
+    fn seek(&mut self, pos: f32) {
+        self.clear_next();
+        self.ended = false;
+        if self.path.is_some() {
+            self.position = pos.clamp(0., PlayEngine::duration(self));
+        }
+    }
+    fn position(&self) -> f32 {
+        self.position
+    }
+    fn duration(&self) -> f32 {
+        self.path
+            .as_ref()
+            .and_then(|path| self.durations.get(path))
+            .copied()
+            .unwrap_or(0.)
+    }
+    fn is_play(&self) -> bool {
+        self.playing
+    }
+    fn is_over(&self) -> bool {
+        self.path.is_none() || self.position >= PlayEngine::duration(self)
+    }
+    fn queue_next(&mut self, path: &str, _crossfade: f32) -> bool {
+        self.clear_next();
+        if self.path.is_none() || !self.durations.contains_key(path) {
+            return false;
+        }
+        self.next = Some(path.to_string());
+        true
+    }
+    fn clear_next(&mut self) {
+        self.next = None;
+    }
+    fn next_path(&self) -> Option<&str> {
+        self.next.as_deref()
+    }
+    fn loop_region(&self) -> Option<(f32, f32)> {
+        self.loop_region
+    }
+    fn set_loop_region(&mut self, region: Option<(f32, f32)>) {
+        self.loop_region = region;
+    }
+    fn speed(&self) -> f32 {
+        self.speed
+    }
+    fn set_speed(&mut self, speed: f32, _keep_pitch: bool) {
+        self.speed = speed;
+    }
+    fn fade_out(&mut self, _secs: f32) {}
+    fn restore_volume(&mut self) {}
+    fn set_equalizer(&mut self, _gains: &[f32], _preamp: f32) {}
+    fn poll_events(&mut self) -> Vec<AudioEvent> {
+        // 已排队的下一首到点后切换过去
+        if self.playing && self.is_over() {
+            if let Some(next) = self.next.take() {
+                self.start(next);
+            }
+        }
+        if self.playing {
+            self.events.push(AudioEvent::Position(self.position));
+            if !self.ended && self.is_over() && self.next.is_none() {
+                self.ended = true;
+                let path = self.path.clone().unwrap_or_default();
+                self.events.push(AudioEvent::Ended(path));
+            }
+        }
+        std::mem::take(&mut self.events)
+    }
+}
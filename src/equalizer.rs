@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use iced::{
+    widget::{button, checkbox, column, pick_list, row, slider, text, text_input, vertical_slider},
+    Alignment,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::ConfigMessage, engine::PlayEngine, Message, SilkPlayer, View};
+
+pub const EQ_BANDS: [f32; 10] = [
+    31., 62., 125., 250., 500., 1000., 2000., 4000., 8000., 16000.,
+]; // 各频段中心频率，单位：Hz
+const MAX_GAIN: f32 = 12.0; // 单个频段及前置增益的调节范围，单位：dB
+
+/// 均衡器预设，gains 与 EQ_BANDS 一一对应
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EqPreset {
+    pub name: String,
+    pub gains: [f32; 10],
+}
+impl EqPreset {
+    fn new(name: &str, gains: [f32; 10]) -> Self {
+        Self {
+            name: name.to_string(),
+            gains,
+        }
+    }
+
+    /// 内置预设
+    pub fn builtin() -> Vec<EqPreset> {
+        vec![
+            EqPreset::new("平直", [0., 0., 0., 0., 0., 0., 0., 0., 0., 0.]),
+            EqPreset::new("流行", [-1., 0., 2., 4., 4., 2., 0., -1., -1., -1.]),
+            EqPreset::new("摇滚", [5., 4., 3., 1., -1., -1., 1., 3., 4., 5.]),
+            EqPreset::new("人声", [-2., -2., -1., 1., 3., 4., 3., 2., 0., -1.]),
+            EqPreset::new("低音增强", [6., 5., 4., 2., 0., 0., 0., 0., 0., 0.]),
+            EqPreset::new("高音增强", [0., 0., 0., 0., 0., 1., 2., 4., 5., 6.]),
+            EqPreset::new("古典", [4., 3., 2., 1., -1., -1., 0., 2., 3., 4.]),
+            EqPreset::new("爵士", [3., 2., 1., 2., -1., -1., 0., 1., 2., 3.]),
+            EqPreset::new("电子", [5., 4., 1., 0., -2., 1., 0., 1., 4., 5.]),
+        ]
+    }
+}
+impl std::fmt::Display for EqPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "跟随全局设置")
+        } else {
+            write!(f, "{}", self.name)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EqConfig {
+    pub enabled: bool,
+    pub preset: String, // 当前选择的预设，手动调节后为空
+    pub gains: [f32; 10],
+    pub preamp: f32,
+    pub user_presets: Vec<EqPreset>,
+    pub tag_presets: HashMap<String, String>, // 歌单目录 -> 预设名
+}
+impl Default for EqConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            preset: "平直".to_string(),
+            gains: [0.; 10],
+            preamp: 0.,
+            user_presets: vec![],
+            tag_presets: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ChangeEq {
+    Enabled(bool),
+    Preset(EqPreset),
+    Band(usize, f32),
+    Preamp(f32),
+    PresetName(String),
+    SavePreset,
+    DeletePreset,
+    TagPreset(String, EqPreset), // 歌单目录，预设名为空表示跟随全局设置
+}
+
+impl EqConfig {
+    /// 内置预设与用户预设
+    pub fn presets(&self) -> Vec<EqPreset> {
+        let mut presets = EqPreset::builtin();
+        presets.extend(self.user_presets.iter().cloned());
+        presets
+    }
+
+    pub fn find(&self, name: &str) -> Option<EqPreset> {
+        self.presets().into_iter().find(|preset| preset.name == name)
+    }
+
+    /// 根据歌曲所在歌单选择生效的增益，未启用时返回 None
+    pub fn gains_for(&self, tag_paths: &[&str]) -> Option<[f32; 10]> {
+        if !self.enabled {
+            return None;
+        }
+        let tag_preset = tag_paths
+            .iter()
+            .find_map(|path| self.tag_presets.get(*path))
+            .and_then(|name| self.find(name));
+        Some(tag_preset.map_or(self.gains, |preset| preset.gains))
+    }
+
+    /// 修改设置，preset_name 为保存预设时输入的名称
+    pub fn change(&mut self, change: &ChangeEq, preset_name: &str) {
+        match change {
+            ChangeEq::Enabled(enabled) => self.enabled = *enabled,
+            ChangeEq::Preset(preset) => {
+                self.preset = preset.name.to_string();
+                self.gains = preset.gains;
+            }
+            ChangeEq::Band(index, gain) => {
+                if let Some(band) = self.gains.get_mut(*index) {
+                    *band = gain.clamp(-MAX_GAIN, MAX_GAIN);
+                    self.preset = String::new();
+                }
+            }
+            ChangeEq::Preamp(preamp) => self.preamp = preamp.clamp(-MAX_GAIN, MAX_GAIN),
+            ChangeEq::PresetName(_) => {}
+            ChangeEq::SavePreset => {
+                let name = preset_name.trim();
+                if name.is_empty() || EqPreset::builtin().iter().any(|preset| preset.name == name) {
+                    return;
+                }
+                let preset = EqPreset::new(name, self.gains);
+                match self.user_presets.iter_mut().find(|preset| preset.name == name) {
+                    Some(user_preset) => *user_preset = preset,
+                    None => self.user_presets.push(preset),
+                }
+                self.preset = name.to_string();
+            }
+            ChangeEq::DeletePreset => {
+                let name = self.preset.to_string();
+                self.user_presets.retain(|preset| preset.name != name);
+                self.tag_presets.retain(|_, preset| *preset != name);
+                self.preset = String::new();
+            }
+            ChangeEq::TagPreset(path, preset) => {
+                if preset.name.is_empty() {
+                    self.tag_presets.remove(path);
+                } else {
+                    self.tag_presets
+                        .insert(path.to_string(), preset.name.to_string());
+                }
+            }
+        }
+    }
+}
+
+impl<E: PlayEngine> SilkPlayer<E> {
+    /// 将均衡器设置应用到当前播放
+    pub fn apply_eq(&mut self) {
+        let tag_paths: Vec<&str> = self
+            .current_song
+            .tags
+            .iter()
+            .map(|tag| tag.path.as_str())
+            .collect();
+        match self.setting.eq.gains_for(&tag_paths) {
+            Some(gains) => self.audio.set_equalizer(&gains, self.setting.eq.preamp),
+            None => self.audio.set_equalizer(&[0.; 10], 0.),
+        }
+    }
+}
+
+impl SilkPlayer {
+    pub fn eq_view(&self) -> View<'_> {
+        let eq = &self.setting.eq;
+        let change = |change| Message::ChangeConfig(ConfigMessage::ChangeEq(change));
+
+        let presets = pick_list(eq.presets(), eq.find(&eq.preset), move |preset| {
+            change(ChangeEq::Preset(preset))
+        })
+        .placeholder("自定义");
+        let is_user_preset = eq.user_presets.iter().any(|preset| preset.name == eq.preset);
+        let preset = row!(
+            checkbox("启用", eq.enabled).on_toggle(move |v| change(ChangeEq::Enabled(v))),
+            presets,
+            text_input("预设名称", &self.app_control.eq_preset_name)
+                .on_input(move |value| change(ChangeEq::PresetName(value)))
+                .width(150),
+            button("保存预设").on_press(change(ChangeEq::SavePreset)),
+            button("删除预设").on_press_maybe(if is_user_preset {
+                Some(change(ChangeEq::DeletePreset))
+            } else {
+                None
+            }),
+        )
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let preamp = row!(
+            "前置增益",
+            slider(-MAX_GAIN..=MAX_GAIN, eq.preamp, move |v| change(ChangeEq::Preamp(v)))
+                .step(0.5)
+                .width(300),
+            text(format!("{:+.1} dB", eq.preamp)),
+        )
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let mut bands = row!().spacing(20);
+        for (index, (freq, gain)) in EQ_BANDS.iter().zip(eq.gains).enumerate() {
+            let freq = if *freq >= 1000. {
+                format!("{}k", freq / 1000.)
+            } else {
+                freq.to_string()
+            };
+            bands = bands.push(
+                column!(
+                    text(format!("{:+.1}", gain)).size(14),
+                    vertical_slider(-MAX_GAIN..=MAX_GAIN, gain, move |v| {
+                        change(ChangeEq::Band(index, v))
+                    })
+                    .step(0.5)
+                    .height(120),
+                    text(freq).size(14),
+                )
+                .spacing(5)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        column!("", "均衡器", preset, preamp, bands).spacing(5).into()
+    }
+
+    /// 歌单详情页中为该歌单指定均衡器预设
+    pub fn tag_eq_view(&self, path: &str) -> View<'_> {
+        let eq = &self.setting.eq;
+        let follow = EqPreset::new("", [0.; 10]);
+        let mut presets = vec![follow.clone()];
+        presets.extend(eq.presets());
+        let selected = eq
+            .tag_presets
+            .get(path)
+            .and_then(|name| eq.find(name))
+            .unwrap_or(follow);
+        let path = path.to_string();
+        row!(
+            "均衡器",
+            pick_list(presets, Some(selected), move |preset| {
+                Message::ChangeConfig(ConfigMessage::ChangeEq(ChangeEq::TagPreset(
+                    path.to_string(),
+                    preset,
+                )))
+            })
+        )
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into()
+    }
+}
@@ -14,7 +14,13 @@ use iced::{
     window, Command, Event,
 };
 
-use crate::{config::ConfigMessage, Message, MyCommand, SilkPlayer, SongControl, Status, Tab};
+use crate::{
+    bookmark::BookmarkControl,
+    config::ConfigMessage,
+    play::{AbLoop, SPEED_STEP},
+    sleep::SleepControl, Message, MyCommand, SilkPlayer, SongControl, Status,
+    Tab,
+};
 
 impl SilkPlayer {
     // 注册全局热键
@@ -69,6 +75,19 @@ impl SilkPlayer {
                 "l" => Some(Message::ChangeTab(Tab::List)),
                 "s" => Some(Message::ChangeTab(Tab::Option)),
                 "h" => Some(Message::ChangeTab(Tab::Home)),
+                "[" => Some(Message::ChangeConfig(ConfigMessage::ChangeSpeed(
+                    self.audio.speed() - SPEED_STEP,
+                ))),
+                "]" => Some(Message::ChangeConfig(ConfigMessage::ChangeSpeed(
+                    self.audio.speed() + SPEED_STEP,
+                ))),
+                "\\" => Some(Message::ChangeConfig(ConfigMessage::ChangeSpeed(1.0))),
+                "," => Some(Message::SongControl(SongControl::AbLoop(AbLoop::A))),
+                "." => Some(Message::SongControl(SongControl::AbLoop(AbLoop::B))),
+                "/" => Some(Message::SongControl(SongControl::AbLoop(AbLoop::Clear))),
+                ";" => Some(Message::SongControl(SongControl::AbLoop(AbLoop::Save))),
+                "t" => Some(Message::SleepTimer(SleepControl::Cycle)),
+                "b" => Some(Message::Bookmark(BookmarkControl::Add)),
                 _ => None
             }
         } else if let Key::Named(n) = key {
@@ -80,10 +99,10 @@ impl SilkPlayer {
                     ConfigMessage::ChangeVolume(self.audio.volume() - 0.01),
                 )),
                 Named::ArrowRight if is_ctrl => {
-                    Some(Message::SongControl(SongControl::PlayNext(true)))
+                    Some(Message::SongControl(SongControl::Chapter(true)))
                 }
                 Named::ArrowLeft if is_ctrl => {
-                    Some(Message::SongControl(SongControl::PlayNext(false)))
+                    Some(Message::SongControl(SongControl::Chapter(false)))
                 }
                 Named::Space => Some(Message::SongControl(SongControl::PlayOrPause)),
                 Named::Escape => Some(Message::ToggleEsc),
@@ -271,11 +290,11 @@ impl GolbalHotKey {
     pub fn defaut_list() -> Vec<Self> {
         let mut vec = vec![
             Self::new(
-                Message::SongControl(SongControl::PlayNext(false)),
+                Message::SongControl(SongControl::Chapter(false)),
                 vec![Keycode::LControl, Keycode::Numpad4],
             ),
             Self::new(
-                Message::SongControl(SongControl::PlayNext(true)),
+                Message::SongControl(SongControl::Chapter(true)),
                 vec![Keycode::LControl, Keycode::Numpad6],
             ),
             Self::new(
@@ -286,6 +305,10 @@ impl GolbalHotKey {
                 Message::DesktopLyricWindow,
                 vec![Keycode::LControl, Keycode::NumpadDivide],
             ),
+            Self::new(
+                Message::SleepTimer(SleepControl::Cancel),
+                vec![Keycode::LControl, Keycode::Numpad0],
+            ),
         ];
 
         let mut copy = vec![];
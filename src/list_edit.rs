@@ -0,0 +1,274 @@
+use std::time::{Duration, Instant};
+
+use iced::{
+    theme,
+    widget::{button, container, row, text, MouseArea},
+    Alignment, Command, Length,
+};
+
+use crate::{style, util, Message, MusicInfo, SilkPlayer, SongControl, View};
+
+const DOUBLE_CLICK: Duration = Duration::from_millis(400); // 两次点击间隔小于此值视为双击
+
+/// 播放列表编辑：多选、移动、拖拽排序、移除与裁剪
+#[derive(Debug, Clone)]
+pub enum ListEdit {
+    Press(String),   // 在歌曲上按下鼠标，可能开始拖拽
+    Release(String), // 在歌曲上松开鼠标，与按下的是同一首时视为点击选择，双击则播放
+    MoveUp,
+    MoveDown,
+    MoveTop,
+    MoveBottom,
+    RemoveSelected,
+    CropSelected, // 只保留选中的歌曲
+    ClearSelection,
+}
+
+/// 播放列表的选择状态
+#[derive(Debug, Default)]
+pub struct ListSelection {
+    pub selected: Vec<String>, // 选中歌曲的路径
+    anchor: Option<String>,    // Shift 连续选择的起点
+    pressed: Option<String>,   // 按下鼠标时所在的歌曲
+    last_click: Option<(String, Instant)>, // 上次点击的歌曲和时间，用于识别双击
+}
+
+impl ListSelection {
+    pub fn contains(&self, path: &str) -> bool {
+        self.selected.iter().any(|item| item == path)
+    }
+
+    /// 记录一次点击，与上次点击的是同一首且间隔很短时视为双击
+    fn double_click(&mut self, path: &str) -> bool {
+        let now = Instant::now();
+        let is_double = matches!(
+            &self.last_click,
+            Some((last, time)) if last == path && now.duration_since(*time) < DOUBLE_CLICK
+        );
+        self.last_click = (!is_double).then(|| (path.to_string(), now));
+        is_double
+    }
+}
+
+impl SilkPlayer {
+    pub fn list_edit(&mut self, edit: ListEdit) -> Command<Message> {
+        let mut list = match self.play_list.all_list.try_lock() {
+            Ok(all_list) => all_list.to_vec(),
+            Err(_) => return Command::none(),
+        };
+        let selection = &mut self.app_control.list_selection;
+        let mut play = None;
+        let changed = match edit {
+            ListEdit::Press(path) => {
+                selection.pressed = Some(path);
+                false
+            }
+            ListEdit::Release(path) => match selection.pressed.take() {
+                Some(pressed) if pressed != path => {
+                    // 拖拽：按下的歌曲未选中时只移动这一首
+                    if !selection.contains(&pressed) {
+                        selection.selected = vec![pressed.to_string()];
+                        selection.anchor = Some(pressed.to_string());
+                    }
+                    drop_selected(&mut list, &selection.selected, &pressed, &path)
+                }
+                Some(_) => {
+                    let is_shift = self.key_modify.iter().any(|modifiers| modifiers.shift());
+                    let is_ctrl = self.key_modify.iter().any(|modifiers| modifiers.control());
+                    if !is_shift && !is_ctrl && selection.double_click(&path) {
+                        play = list.iter().find(|item| item.path == path).cloned();
+                    } else {
+                        select(selection, &list, &path, is_shift, is_ctrl);
+                    }
+                    false
+                }
+                None => false,
+            },
+            ListEdit::MoveUp => move_step(&mut list, &selection.selected, true),
+            ListEdit::MoveDown => move_step(&mut list, &selection.selected, false),
+            ListEdit::MoveTop | ListEdit::MoveBottom => {
+                let (mut picked, rest): (Vec<MusicInfo>, Vec<MusicInfo>) = list
+                    .drain(..)
+                    .partition(|item| selection.contains(&item.path));
+                if matches!(edit, ListEdit::MoveTop) {
+                    picked.extend(rest);
+                    list = picked;
+                } else {
+                    list = rest;
+                    list.extend(picked);
+                }
+                true
+            }
+            ListEdit::RemoveSelected | ListEdit::CropSelected => {
+                let keep = matches!(edit, ListEdit::CropSelected);
+                let len = list.len();
+                list.retain(|item| selection.contains(&item.path) == keep);
+                selection.selected.clear();
+                selection.anchor = None;
+                list.len() != len
+            }
+            ListEdit::ClearSelection => {
+                selection.selected.clear();
+                selection.anchor = None;
+                false
+            }
+        };
+        if let Some(music_info) = play {
+            return self.change_play_list(SongControl::First(music_info));
+        }
+        if !changed {
+            return Command::none();
+        }
+
+        let current_removed = !self.current_song.path.is_empty()
+            && !list.iter().any(|item| item.path == self.current_song.path);
+        let is_empty = list.is_empty();
+        if let Ok(mut all_list) = self.play_list.all_list.try_lock() {
+            *all_list = list;
+        }
+        util::log("play list edited");
+        if is_empty {
+            self.clear_play();
+            return Command::none();
+        }
+        self.reset_next_song();
+        self.play_list.filter();
+        if current_removed {
+            // 当前播放被移出播放列表，则自动播放下一首
+            self.app_control.list_anchor = None;
+            self.next_song();
+        }
+        self.save_play_status();
+        Command::none()
+    }
+
+    /// 播放列表中的一行，可点击选择、双击播放、拖拽排序
+    pub fn list_item_view<'a>(&'a self, content: View<'a>, music_info: &MusicInfo) -> View<'a> {
+        let selected = self.app_control.list_selection.contains(&music_info.path);
+        let item = container(content).width(Length::Fill).padding(5);
+        let item = if selected {
+            item.style(theme::Container::Custom(Box::new(
+                style::ContainerStyle::Primary(0.3),
+            )))
+        } else {
+            item
+        };
+        MouseArea::new(item)
+            .on_press(Message::ListEdit(ListEdit::Press(music_info.path.to_string())))
+            .on_release(Message::ListEdit(ListEdit::Release(music_info.path.to_string())))
+            .into()
+    }
+
+    /// 选中歌曲后显示的编辑工具栏
+    pub fn list_edit_view(&self) -> Option<View<'_>> {
+        let count = self.app_control.list_selection.selected.len();
+        if count == 0 {
+            return None;
+        }
+        let edit_button = |label: &str, edit: ListEdit| {
+            button(text(label.to_string()).size(14))
+                .on_press(Message::ListEdit(edit))
+                .style(theme::Button::Custom(Box::new(
+                    style::ButtonType::Info.default(),
+                )))
+        };
+        Some(
+            row!(
+                text(format!("已选 {} 首", count)).size(14),
+                edit_button("上移", ListEdit::MoveUp),
+                edit_button("下移", ListEdit::MoveDown),
+                edit_button("置顶", ListEdit::MoveTop),
+                edit_button("置底", ListEdit::MoveBottom),
+                edit_button("移除所选", ListEdit::RemoveSelected),
+                edit_button("仅保留所选", ListEdit::CropSelected),
+                edit_button("取消选择", ListEdit::ClearSelection),
+            )
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .into(),
+        )
+    }
+}
+
+/// 点击选择：单击只选这一首，Ctrl 切换选中，Shift 从起点连续选择
+fn select(
+    selection: &mut ListSelection,
+    list: &[MusicInfo],
+    path: &str,
+    is_shift: bool,
+    is_ctrl: bool,
+) {
+    let index_of = |path: &str| list.iter().position(|item| item.path == path);
+    let range = selection
+        .anchor
+        .as_deref()
+        .and_then(index_of)
+        .zip(index_of(path));
+    match range {
+        Some((from, to)) if is_shift => {
+            let (from, to) = (from.min(to), from.max(to));
+            selection.selected = list[from..=to]
+                .iter()
+                .map(|item| item.path.to_string())
+                .collect();
+            return;
+        }
+        _ => {}
+    }
+    if is_ctrl {
+        if selection.contains(path) {
+            selection.selected.retain(|item| item != path);
+        } else {
+            selection.selected.push(path.to_string());
+        }
+    } else if selection.selected.len() == 1 && selection.contains(path) {
+        selection.selected.clear();
+    } else {
+        selection.selected = vec![path.to_string()];
+    }
+    selection.anchor = Some(path.to_string());
+}
+
+/// 选中的歌曲整体上移或下移一位，已到顶或到底的保持不动
+fn move_step(list: &mut [MusicInfo], selected: &[String], up: bool) -> bool {
+    let is_selected = |item: &MusicInfo| selected.contains(&item.path);
+    let mut changed = false;
+    let len = list.len();
+    for step in 0..len {
+        let index = if up { step } else { len - 1 - step };
+        let other = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1).filter(|i| *i < len)
+        };
+        if let Some(other) = other {
+            if is_selected(&list[index]) && !is_selected(&list[other]) {
+                list.swap(index, other);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// 拖拽松开：把选中的歌曲移到目标歌曲处，向下拖时放在目标之后，向上拖时放在目标之前
+fn drop_selected(list: &mut Vec<MusicInfo>, selected: &[String], from: &str, to: &str) -> bool {
+    if selected.iter().any(|path| path == to) {
+        return false;
+    }
+    let (from_index, to_index) = match (
+        list.iter().position(|item| item.path == from),
+        list.iter().position(|item| item.path == to),
+    ) {
+        (Some(from_index), Some(to_index)) => (from_index, to_index),
+        _ => return false,
+    };
+    let (picked, mut rest): (Vec<MusicInfo>, Vec<MusicInfo>) = list
+        .drain(..)
+        .partition(|item| selected.contains(&item.path));
+    let target = rest.iter().position(|item| item.path == to).unwrap_or(0);
+    let index = if from_index < to_index { target + 1 } else { target };
+    rest.splice(index..index, picked);
+    *list = rest;
+    true
+}
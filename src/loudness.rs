@@ -0,0 +1,570 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{fs, thread};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::replaygain::{self, ReplayGain};
+use crate::util;
+use ::silk_player::ThreadPool;
+
+const CACHE_PATH: &str = "loudness.json";
+const REFERENCE_LUFS: f32 = -18.0; // ReplayGain 2.0 参考响度
+const THROTTLE: Duration = Duration::from_millis(300); // 每首歌分析完后的间隔，避免长时间占满 CPU
+const PAUSE_CHECK: Duration = Duration::from_millis(200);
+const SAVE_BATCH: usize = 20; // 每分析多少首写一次缓存文件，暂停、停止和结束时也会写入
+
+static CACHE: Lazy<Mutex<HashMap<String, Loudness>>> = Lazy::new(|| Mutex::new(load_cache()));
+static DIRTY: AtomicBool = AtomicBool::new(false); // 缓存有未写入文件的结果
+
+/// 单首歌曲的响度分析结果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Loudness {
+    pub lufs: f32, // 综合响度，单位：LUFS
+    pub peak: f32, // 真峰值，线性幅度
+    modified: u64, // 分析时文件的修改时间，文件变化后结果失效
+}
+impl Loudness {
+    /// 换算为 ReplayGain 增益，单位：dB
+    pub fn gain(&self) -> f32 {
+        REFERENCE_LUFS - self.lufs
+    }
+}
+
+/// 读取缓存的分析结果，文件修改过则视为没有
+pub fn cached(path: &str) -> Option<Loudness> {
+    let modified = modified(path)?;
+    let cache = CACHE.lock().ok()?;
+    cache
+        .get(path)
+        .filter(|loudness| loudness.modified == modified)
+        .copied()
+}
+
+/// 后台响度分析任务，可暂停、停止，并查询进度
+#[derive(Default, Clone)]
+pub struct LoudnessScan {
+    state: Arc<ScanState>,
+}
+
+#[derive(Default)]
+struct ScanState {
+    running: AtomicBool,
+    paused: AtomicBool,
+    cancel: AtomicBool,
+    done: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl LoudnessScan {
+    /// 分析列表中既没有 ReplayGain 标签、也没有缓存结果的歌曲
+    pub fn start(&self, pool: &ThreadPool, paths: Vec<String>, write_tags: bool) {
+        if self.is_running() {
+            return;
+        }
+        let state = self.state.clone();
+        state.running.store(true, Ordering::SeqCst);
+        state.paused.store(false, Ordering::SeqCst);
+        state.cancel.store(false, Ordering::SeqCst);
+        state.done.store(0, Ordering::SeqCst);
+        state.total.store(paths.len(), Ordering::SeqCst);
+
+        pool.execute(move || {
+            util::log(format!("loudness scan start, {} files", paths.len()));
+            let mut unsaved = 0;
+            for path in paths {
+                if !state.wait() {
+                    break;
+                }
+                if cached(&path).is_none() && ReplayGain::read_tags(&path).track_gain.is_none() {
+                    match analyze(&path, &state) {
+                        Ok(Some(loudness)) => {
+                            store(&path, loudness, write_tags);
+                            unsaved += 1;
+                            if unsaved >= SAVE_BATCH {
+                                flush_cache();
+                                unsaved = 0;
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            util::log_err(format!("loudness analyze err path={} err={}", path, err))
+                        }
+                    }
+                    thread::sleep(THROTTLE);
+                }
+                state.done.fetch_add(1, Ordering::SeqCst);
+            }
+            flush_cache();
+            state.running.store(false, Ordering::SeqCst);
+            util::log("loudness scan done");
+        });
+    }
+
+    pub fn pause(&self) {
+        self.state.paused.store(true, Ordering::SeqCst);
+    }
+    pub fn resume(&self) {
+        self.state.paused.store(false, Ordering::SeqCst);
+    }
+    pub fn stop(&self) {
+        self.state.cancel.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state.running.load(Ordering::SeqCst)
+    }
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::SeqCst)
+    }
+    /// 已处理数量和总数
+    pub fn progress(&self) -> (usize, usize) {
+        (
+            self.state.done.load(Ordering::SeqCst),
+            self.state.total.load(Ordering::SeqCst),
+        )
+    }
+}
+
+impl ScanState {
+    /// 暂停时阻塞等待，返回 false 表示任务已停止
+    fn wait(&self) -> bool {
+        if self.paused.load(Ordering::SeqCst) {
+            flush_cache();
+        }
+        while self.paused.load(Ordering::SeqCst) && !self.cancel.load(Ordering::SeqCst) {
+            thread::sleep(PAUSE_CHECK);
+        }
+        !self.cancel.load(Ordering::SeqCst)
+    }
+}
+
+fn store(path: &str, loudness: Loudness, write_tags: bool) {
+    let mut loudness = loudness;
+    if write_tags {
+        match replaygain::write_track_gain(path, loudness.gain(), loudness.peak) {
+            // 写入标签会改变文件修改时间
+            Ok(_) => loudness.modified = modified(path).unwrap_or(loudness.modified),
+            Err(err) => util::log_err(format!("write replaygain err path={} err={}", path, err)),
+        }
+    }
+    util::log(format!(
+        "loudness {} {:.2} LUFS peak {:.4}",
+        path, loudness.lufs, loudness.peak
+    ));
+    if let Ok(mut cache) = CACHE.lock() {
+        cache.insert(path.to_string(), loudness);
+        DIRTY.store(true, Ordering::SeqCst);
+    }
+}
+
+/// 解码整首歌曲并计算 EBU R128 综合响度与真峰值，歌曲过短时返回 None
+fn analyze(path: &str, state: &ScanState) -> Result<Option<Loudness>, Error> {
+    let modified = match modified(path) {
+        Some(modified) => modified,
+        None => return Ok(None),
+    };
+    let mut meter: Option<Meter> = None;
+    let mut packets = 0;
+    decode(path, |samples, channels, sample_rate| {
+        packets += 1;
+        if packets % 64 == 0 && !state.wait() {
+            return false;
+        }
+        let meter = meter.get_or_insert_with(|| Meter::new(channels, sample_rate));
+        meter.process(samples);
+        true
+    })?;
+    if state.cancel.load(Ordering::SeqCst) {
+        return Ok(None);
+    }
+    Ok(meter.and_then(|meter| {
+        meter.integrated().map(|lufs| Loudness {
+            lufs: lufs as f32,
+            peak: meter.peak as f32,
+            modified,
+        })
+    }))
+}
+
+/// 流式解码音频文件，按包回调交错排列的采样，回调返回 false 时停止
+pub fn decode<F>(path: &str, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(&[f32], usize, u32) -> bool,
+{
+    let file = fs::File::open(path)?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = std::path::Path::new(path).extension() {
+        hint.with_extension(&ext.to_string_lossy());
+    }
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let mut format = probed.format;
+    let track = format
+        .default_track()
+        .ok_or(Error::Unsupported("no audio track"))?;
+    let track_id = track.id;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut buf: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                let spec = *decoded.spec();
+                let capacity = decoded.capacity() as u64;
+                if buf.as_ref().is_none_or(|buf| {
+                    buf.capacity() < capacity as usize * spec.channels.count()
+                }) {
+                    buf = Some(SampleBuffer::new(capacity, spec));
+                }
+                if let Some(ref mut buf) = buf {
+                    buf.copy_interleaved_ref(decoded);
+                    if !f(buf.samples(), spec.channels.count(), spec.rate) {
+                        break;
+                    }
+                }
+            }
+            // 个别损坏的包直接跳过
+            Err(Error::DecodeError(_)) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// 二阶 IIR 滤波器（直接 II 型转置）
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    z: [f64; 2],
+}
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[1] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[2] * y;
+        y
+    }
+}
+
+/// K 计权的两级滤波器，系数按采样率计算（同 libebur128）
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    use std::f64::consts::PI;
+
+    // 第一级：高频搁架，模拟头部的声学影响
+    let f0 = 1681.974450955533;
+    let g = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(g / 20.);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1. + k / q + k * k;
+    let pre = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2. * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [1., 2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0],
+        z: [0.; 2],
+    };
+
+    // 第二级：RLB 高通
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1. + k / q + k * k;
+    let rlb = Biquad {
+        b: [1., -2., 1.],
+        a: [1., 2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0],
+        z: [0.; 2],
+    };
+    [pre, rlb]
+}
+
+const OVERSAMPLE: usize = 4;
+const TAPS_PER_PHASE: usize = 12;
+
+/// EBU R128 / ITU-R BS.1770 响度计
+struct Meter {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<[Biquad; 2]>,
+    sub_len: usize, // 100ms 的帧数，400ms 的测量块以 75% 重叠滑动
+    sub_frames: usize,
+    sub_sum: f64,
+    subs: Vec<f64>,
+    blocks: Vec<f64>, // 每个测量块的均方能量
+    coeffs: Vec<f64>, // 4 倍过采样插值滤波器
+    history: Vec<[f64; TAPS_PER_PHASE]>,
+    peak: f64,
+}
+impl Meter {
+    fn new(channels: usize, sample_rate: u32) -> Meter {
+        // 5.1 声道时 LFE 不计入，环绕声道权重 1.41
+        let weights = (0..channels)
+            .map(|i| match (channels, i) {
+                (6, 3) => 0.,
+                (6, 4) | (6, 5) => 1.41,
+                _ => 1.,
+            })
+            .collect();
+
+        let len = OVERSAMPLE * TAPS_PER_PHASE;
+        let center = (len - 1) as f64 / 2.;
+        let coeffs = (0..len)
+            .map(|n| {
+                let t = (n as f64 - center) / OVERSAMPLE as f64;
+                let sinc = if t == 0. {
+                    1.
+                } else {
+                    (std::f64::consts::PI * t).sin() / (std::f64::consts::PI * t)
+                };
+                let window =
+                    0.5 - 0.5 * (2. * std::f64::consts::PI * (n as f64 + 0.5) / len as f64).cos();
+                sinc * window
+            })
+            .collect();
+
+        Meter {
+            channels,
+            weights,
+            filters: vec![k_weighting(sample_rate as f64); channels],
+            sub_len: (sample_rate as usize / 10).max(1),
+            sub_frames: 0,
+            sub_sum: 0.,
+            subs: Vec::with_capacity(4),
+            blocks: vec![],
+            coeffs,
+            history: vec![[0.; TAPS_PER_PHASE]; channels],
+            peak: 0.,
+        }
+    }
+
+    fn process(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (ch, sample) in frame.iter().enumerate() {
+                let x = *sample as f64;
+                let [pre, rlb] = &mut self.filters[ch];
+                let y = rlb.process(pre.process(x));
+                self.sub_sum += self.weights[ch] * y * y;
+                self.true_peak(ch, x);
+            }
+            self.sub_frames += 1;
+            if self.sub_frames == self.sub_len {
+                if self.subs.len() == 4 {
+                    self.subs.remove(0);
+                }
+                self.subs.push(self.sub_sum);
+                if self.subs.len() == 4 {
+                    let sum: f64 = self.subs.iter().sum();
+                    self.blocks.push(sum / (4 * self.sub_len) as f64);
+                }
+                self.sub_frames = 0;
+                self.sub_sum = 0.;
+            }
+        }
+    }
+
+    /// 过采样后取峰值，捕捉采样点之间的峰
+    fn true_peak(&mut self, ch: usize, x: f64) {
+        let history = &mut self.history[ch];
+        history.copy_within(0..TAPS_PER_PHASE - 1, 1);
+        history[0] = x;
+        self.peak = self.peak.max(x.abs());
+        for phase in 0..OVERSAMPLE {
+            let mut y = 0.;
+            for (k, h) in history.iter().enumerate() {
+                y += self.coeffs[phase + k * OVERSAMPLE] * h;
+            }
+            self.peak = self.peak.max(y.abs());
+        }
+    }
+
+    /// 绝对门限 -70 LUFS，相对门限低于平均值 10 LU
+    fn integrated(&self) -> Option<f64> {
+        let loudness = |z: f64| -0.691 + 10. * z.log10();
+        let gated: Vec<f64> = self
+            .blocks
+            .iter()
+            .copied()
+            .filter(|z| *z > 0. && loudness(*z) > -70.)
+            .collect();
+        if gated.is_empty() {
+            return None;
+        }
+        let relative = loudness(gated.iter().sum::<f64>() / gated.len() as f64) - 10.;
+        let gated: Vec<f64> = gated
+            .into_iter()
+            .filter(|z| loudness(*z) > relative)
+            .collect();
+        if gated.is_empty() {
+            return None;
+        }
+        Some(loudness(gated.iter().sum::<f64>() / gated.len() as f64))
+    }
+}
+
+/// 文件的修改时间，单位：s
+pub fn modified(path: &str) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+fn load_cache() -> HashMap<String, Loudness> {
+    let cache_path = format!("{}/{}", util::data_dir(), CACHE_PATH);
+    match fs::read_to_string(&cache_path) {
+        Err(_) => HashMap::new(),
+        Ok(data) => match serde_json::from_str(&data) {
+            Ok(cache) => cache,
+            Err(err) => {
+                util::log_err(format!("parse loudness cache error {}", err));
+                HashMap::new()
+            }
+        },
+    }
+}
+
+/// 把未写入的分析结果写入缓存文件
+fn flush_cache() {
+    if !DIRTY.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    if let Ok(cache) = CACHE.lock() {
+        save_cache(&cache);
+    }
+}
+
+fn save_cache(cache: &HashMap<String, Loudness>) {
+    let data_dir = &util::data_dir();
+    if !util::file_exist(data_dir) {
+        if let Err(err) = fs::create_dir(data_dir) {
+            util::log_err(format!(
+                "save loudness cache;Create data dir errolr: {}",
+                err
+            ));
+            return;
+        }
+    }
+    let cache_path = format!("{}/{}", data_dir, CACHE_PATH);
+    match serde_json::to_string(cache) {
+        Err(err) => util::log_err(format!("save loudness cache error {}", err)),
+        Ok(data) => {
+            if let Err(err) = fs::write(&cache_path, data) {
+                util::log_err(format!(
+                    "save loudness cache error {};path:{}",
+                    err, cache_path
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48000;
+
+    /// 交错排列的正弦波，amplitude 为峰值幅度
+    fn sine(freq: f64, amplitude: f64, secs: f64, channels: usize) -> Vec<f32> {
+        let frames = (secs * RATE as f64) as usize;
+        (0..frames)
+            .flat_map(|n| {
+                let x = amplitude * (2. * std::f64::consts::PI * freq * n as f64 / RATE as f64).sin();
+                std::iter::repeat_n(x as f32, channels)
+            })
+            .collect()
+    }
+
+    fn measure(samples: &[f32], channels: usize) -> Meter {
+        let mut meter = Meter::new(channels, RATE);
+        // 按解码时的包大小分批送入
+        for chunk in samples.chunks(1152 * channels) {
+            meter.process(chunk);
+        }
+        meter
+    }
+
+    fn db(value: f64) -> f64 {
+        10f64.powf(value / 20.)
+    }
+
+    #[test]
+    fn stereo_sine_1k_at_minus_23_dbfs() {
+        // EBU Tech 3341 测试 1：双声道 1 kHz、-23 dBFS 正弦，综合响度 -23 LUFS
+        let meter = measure(&sine(1000., db(-23.), 10., 2), 2);
+        let lufs = meter.integrated().unwrap();
+        assert!((lufs + 23.).abs() < 0.1, "lufs={}", lufs);
+        assert!((meter.peak - db(-23.)).abs() < 0.001, "peak={}", meter.peak);
+    }
+
+    #[test]
+    fn stereo_sine_1k_at_minus_33_dbfs() {
+        // EBU Tech 3341 测试 2：-33 dBFS 正弦，综合响度 -33 LUFS
+        let lufs = measure(&sine(1000., db(-33.), 10., 2), 2).integrated().unwrap();
+        assert!((lufs + 33.).abs() < 0.1, "lufs={}", lufs);
+    }
+
+    #[test]
+    fn mono_is_3db_below_stereo() {
+        let lufs = measure(&sine(1000., db(-23.), 10., 1), 1).integrated().unwrap();
+        assert!((lufs + 26.01).abs() < 0.1, "lufs={}", lufs);
+    }
+
+    #[test]
+    fn silence_has_no_loudness() {
+        let meter = measure(&vec![0.; RATE as usize * 10 * 2], 2);
+        assert_eq!(meter.integrated(), None);
+        assert_eq!(meter.peak, 0.);
+    }
+
+    #[test]
+    fn silence_is_gated_out() {
+        // 一半静音不拉低综合响度
+        let mut samples = sine(1000., db(-23.), 10., 2);
+        samples.extend(vec![0.; RATE as usize * 10 * 2]);
+        let lufs = measure(&samples, 2).integrated().unwrap();
+        assert!((lufs + 23.).abs() < 0.1, "lufs={}", lufs);
+    }
+
+    #[test]
+    fn true_peak_catches_inter_sample_peaks() {
+        // fs/4 的正弦相位偏 45° 时，采样点只落在峰值的 0.707 处
+        let samples: Vec<f32> = (0..RATE as usize)
+            .map(|n| (0.5 * (std::f64::consts::FRAC_PI_2 * n as f64 + std::f64::consts::FRAC_PI_4).sin()) as f32)
+            .collect();
+        let meter = measure(&samples, 1);
+        let sample_peak = samples.iter().fold(0f32, |peak, x| peak.max(x.abs())) as f64;
+        assert!(sample_peak < 0.36);
+        assert!((meter.peak - 0.5).abs() < 0.02, "peak={}", meter.peak);
+    }
+}
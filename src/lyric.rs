@@ -0,0 +1,183 @@
+use std::path::{Path, PathBuf};
+
+use encoding_rs::{Encoding, GBK, UTF_16BE, UTF_16LE};
+use serde::{Deserialize, Serialize};
+
+use crate::util;
+
+const ILLEGAL_CHARS: [char; 9] = ['\\', '/', ':', '*', '?', '"', '<', '>', '|'];
+
+/// 内嵌歌词与外部 .lrc 歌词的优先级
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LyricPriority {
+    #[default]
+    EmbeddedFirst,
+    ExternalFirst,
+    EmbeddedOnly,
+}
+impl LyricPriority {
+    pub const ALL: [Self; 3] = [
+        LyricPriority::EmbeddedFirst,
+        LyricPriority::ExternalFirst,
+        LyricPriority::EmbeddedOnly,
+    ];
+}
+impl std::fmt::Display for LyricPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                LyricPriority::EmbeddedFirst => "内嵌优先",
+                LyricPriority::ExternalFirst => "外部 .lrc 优先",
+                LyricPriority::EmbeddedOnly => "仅内嵌",
+            }
+        )
+    }
+}
+
+/// 查找歌曲对应的 .lrc 文件：先找歌曲同目录下的同名文件，
+/// 再在歌词目录中按文件名、“歌手 - 标题”、“标题 - 歌手”、“标题”匹配，不区分大小写
+pub fn find(path: &str, artist: &str, title: &str, lyric_dir: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    let stem = path.file_stem()?.to_string_lossy().to_string();
+
+    if let Some(found) = path.parent().and_then(|dir| find_in(dir, &[stem.as_str()])) {
+        return Some(found);
+    }
+    if lyric_dir.is_empty() {
+        return None;
+    }
+    let mut names = vec![stem];
+    if !title.is_empty() {
+        if !artist.is_empty() {
+            names.push(format!("{} - {}", artist, title));
+            names.push(format!("{} - {}", title, artist));
+        }
+        names.push(title.to_string());
+    }
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    find_in(Path::new(lyric_dir), &names)
+}
+
+/// 按候选名称的顺序在目录中查找 .lrc 文件
+fn find_in(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    let files: Vec<(String, PathBuf)> = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("lrc"))
+        })
+        .filter_map(|path| Some((normalize_name(&path.file_stem()?.to_string_lossy()), path)))
+        .collect();
+
+    names.iter().find_map(|name| {
+        let name = normalize_name(name);
+        files
+            .iter()
+            .find(|(stem, _)| *stem == name)
+            .map(|(_, path)| path.clone())
+    })
+}
+
+/// 文件名中不能出现的字符（如多位歌手的“/”）保存时常被去掉或替换，比较时一并忽略
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !ILLEGAL_CHARS.contains(c) && !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 读取 .lrc 文件并整理成每行一个时间标签的格式，无法读取或没有带时间的歌词时返回 None
+pub fn read(path: &Path) -> Option<String> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            util::log_err(format!("read lrc err path={:?} err={}", path, err));
+            return None;
+        }
+    };
+    let lyrics = normalize(&decode(&bytes));
+    (!lyrics.is_empty()).then_some(lyrics)
+}
+
+/// 识别编码：有 BOM 时按 BOM，否则根据零字节的分布判断无 BOM 的 UTF-16，
+/// 合法的 UTF-8 直接使用，其余按 GBK 解码（中文歌词最常见）
+fn decode(bytes: &[u8]) -> String {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        return encoding
+            .decode_without_bom_handling(&bytes[bom_len..])
+            .0
+            .into_owned();
+    }
+    let zeros_at = |offset: usize| {
+        bytes
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|byte| **byte == 0)
+            .count()
+    };
+    let half = bytes.len() / 2;
+    if half > 0 && zeros_at(1) * 4 > half {
+        return UTF_16LE.decode_without_bom_handling(bytes).0.into_owned();
+    }
+    if half > 0 && zeros_at(0) * 4 > half {
+        return UTF_16BE.decode_without_bom_handling(bytes).0.into_owned();
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => GBK.decode(bytes).0.into_owned(),
+    }
+}
+
+/// 歌词标签只识别 [mm:ss.xx] 格式，这里把一行多个时间标签拆开，
+/// 统一毫秒位数，去掉 [ti:] 等信息标签和不带时间的行，并按时间排序
+fn normalize(text: &str) -> String {
+    let mut lines: Vec<(u64, String)> = vec![];
+    for line in text.lines() {
+        let mut rest = line.trim();
+        let mut times = vec![];
+        while let Some(end) = rest.strip_prefix('[').and_then(|tag| tag.find(']')) {
+            if let Some(time) = parse_time(&rest[1..end + 1]) {
+                times.push(time);
+            }
+            rest = rest[end + 2..].trim_start();
+        }
+        for time in times {
+            lines.push((time, rest.to_string()));
+        }
+    }
+    lines.sort_by_key(|(time, _)| *time);
+
+    let mut lyrics = String::new();
+    for (time, line) in lines {
+        let min = (time / 6000).min(99);
+        lyrics.push_str(&format!(
+            "[{:02}:{:02}.{:02}]{}\n",
+            min,
+            time / 100 % 60,
+            time % 100,
+            line
+        ));
+    }
+    lyrics
+}
+
+/// 解析 mm:ss、mm:ss.xx、mm:ss.xxx 或 mm:ss:xx，返回以 10ms 为单位的时间
+fn parse_time(tag: &str) -> Option<u64> {
+    let (min, secs) = tag.split_once(':')?;
+    let min: u64 = min.trim().parse().ok()?;
+    let (sec, fraction) = match secs.split_once(['.', ':']) {
+        Some((sec, fraction)) => (sec, fraction),
+        None => (secs, "0"),
+    };
+    let sec: u64 = sec.trim().parse().ok()?;
+    if sec >= 60 || fraction.is_empty() || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let centis = format!("{:0<2}", fraction)[..2].parse::<u64>().ok()?;
+    Some((min * 60 + sec) * 100 + centis)
+}
@@ -2,15 +2,30 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod audio;
+mod bookmark;
+mod channel;
+mod chapter;
 mod components;
 mod config;
 mod data;
 mod desktop;
+mod engine;
+mod equalizer;
 mod handle_event;
+mod list_edit;
+mod loudness;
+mod lyric;
+mod pitch;
 mod play;
+mod replaygain;
+mod shuffle;
+mod sleep;
 mod style;
+mod toast;
 mod util;
 mod view;
+mod visualizer;
+mod waveform;
 
 use std::{
     collections::HashMap,
@@ -19,11 +34,13 @@ use std::{
 };
 
 use audio::Audio;
+use bookmark::BookmarkControl;
 use config::{ConfigMessage, Setting};
 
-use data::PlayStatus;
+use data::{PlayStatus, TrackStore};
+use engine::PlayEngine;
 use iced::{
-    event, executor, futures::lock::Mutex, keyboard::Modifiers, multi_window::Application, widget::{column, container, scrollable}, window::{self, settings::PlatformSpecific, Level, Position}, Command, Event, Font, Length, Pixels, Settings, Size, Subscription, Theme
+    event, executor, futures::lock::Mutex, keyboard::Modifiers, multi_window::Application, widget::{column, container, scrollable, text}, window::{self, settings::PlatformSpecific, Level, Position}, Command, Event, Font, Length, Pixels, Settings, Size, Subscription, Theme
 };
 use util::{log, log_err};
 #[cfg(target_os = "windows")]
@@ -34,10 +51,15 @@ use windows::Win32::System::Threading::{
 use ::silk_player::ThreadPool;
 use once_cell::sync::Lazy;
 use play::*;
+use list_edit::ListEdit;
+use sleep::{SleepControl, SleepTimer};
+use toast::Toasts;
 // use thread_priority::*;
 use view::{DetailTab, PageInfo};
+use visualizer::{VisualStyle, Visualizer};
 
 const UPDATE_TIME: f32 = 0.5;
+const AUDIO_TICK: f32 = 0.1; // 取出音频事件的间隔，单位：s
 // const RERRESH: f32 = 1.0;
 static LYRIC_SCROLLABLE_ID: Lazy<scrollable::Id> = Lazy::new(scrollable::Id::unique);
 static PLAY_LIST_SCROLLABLE_ID: Lazy<scrollable::Id> = Lazy::new(scrollable::Id::unique);
@@ -116,6 +138,8 @@ pub enum Tab {
 pub enum Message {
     EventOccurred(Event), // 订阅 iced 的事件转发给 handle_event 处理
     UpdateTime(Instant),
+    Visualize(Instant),
+    AudioTick(Instant), // 取出并处理音频事件
     ScrollLyric(scrollable::Viewport),
 
     MoveWindow(bool), // 是否开始移动窗口
@@ -127,6 +151,10 @@ pub enum Message {
     PlayDetail,
 
     SongControl(SongControl),
+    SleepTimer(SleepControl),
+    ListEdit(ListEdit),
+    DismissToast(usize),
+    Bookmark(BookmarkControl),
 
     ChangeDetail(DetailTab),
     UpdateSongTime(f32),
@@ -150,13 +178,13 @@ pub enum Status {
 }
 
 #[derive(Default)]
-pub struct SilkPlayer {
+pub struct SilkPlayer<E = Audio> {
     status: Status,
     tab: Tab,
     tag: Tag,
     detail_tab: DetailTab,
     current_song: MusicInfo,
-    audio: Audio,
+    audio: E, // 播放引擎，测试时换成 SimEngine
     setting: Setting,
     show_exit_confirm: bool,
     music_list: PageInfo,
@@ -164,6 +192,12 @@ pub struct SilkPlayer {
     app_control: AppControl,             // 控制应用相关行为
     key_modify: Vec<Modifiers>,          // 复杂按键控制
     thread_pool: ThreadPool,             // 使用线程池处理耗时任务
+    analysis_pool: ThreadPool,           // 响度分析单独使用，避免阻塞专辑图片等任务
+    loudness_scan: loudness::LoudnessScan,
+    track_store: TrackStore, // 按歌曲记录的数据
+    sleep_timer: SleepTimer, // 定时关闭
+    visualizer: Visualizer,  // 播放详情页的频谱
+    toasts: Toasts,          // 界面顶部的提示
     command: Arc<Mutex<Vec<MyCommand>>>, // 多线程命令
     album_map: HashMap<String, bool>,
 }
@@ -184,6 +218,18 @@ impl Application for SilkPlayer {
         app.init_list();
         let volume = app.setting.volume;
         app.audio.set_volume(volume);
+        app.audio.set_replay_gain(app.setting.replay_gain);
+        app.audio.set_click_fade(app.setting.click_fade as f32 / 1000.);
+        app.apply_channels();
+        app.audio
+            .spectrum()
+            .set_enabled(app.setting.visualizer != VisualStyle::Off);
+        app.apply_eq();
+        app.app_control.output_devices = audio::output_devices();
+        if !app.setting.output_device.is_empty() {
+            let device = app.setting.output_device.to_string();
+            app.audio.set_output_device(&device);
+        }
 
         app.play_list.size = 50;
 
@@ -265,6 +311,10 @@ impl Application for SilkPlayer {
             Message::SongControl(play_next) => {
                 return self.change_play_list(play_next);
             }
+            Message::SleepTimer(control) => self.sleep_control(control),
+            Message::ListEdit(edit) => return self.list_edit(edit),
+            Message::DismissToast(index) => self.toasts.dismiss(index),
+            Message::Bookmark(control) => self.bookmark_control(control),
             Message::OpenWith(is_dir, mut path, app) => {
                 if is_dir {
                     path = util::get_parent_path(&path);
@@ -293,6 +343,17 @@ impl Application for SilkPlayer {
             Message::UpdateTime(t) => {
                 return self.update_time(t);
             }
+            Message::AudioTick(_) => {
+                self.poll_audio();
+            }
+            Message::Visualize(_) => {
+                if self.audio.is_play() {
+                    let spectrum = self.audio.spectrum();
+                    self.visualizer.update(&spectrum.samples(), spectrum.sample_rate());
+                } else {
+                    self.visualizer.fall();
+                }
+            }
             Message::ScrollLyric(_viewport) => {
                 self.app_control.scroll_seconds = Some(Instant::now());
             }
@@ -342,7 +403,25 @@ impl Application for SilkPlayer {
         } else {
             self.bottom_status_view()
         };
-        column!(content, play_status).into()
+        if self.audio.is_offline() {
+            let banner = container(text("未检测到可用的音频设备，暂时无法发声，正在定期重试…"))
+                .width(Length::Fill)
+                .padding(5)
+                .center_x()
+                .style(iced::theme::Container::Custom(Box::new(
+                    style::ContainerStyle::Primary(0.8),
+                )));
+            return column!(banner)
+                .push_maybe(self.toast_view())
+                .push(content)
+                .push(play_status)
+                .into();
+        }
+        column!()
+            .push_maybe(self.toast_view())
+            .push(content)
+            .push(play_status)
+            .into()
     }
 
     fn subscription(&self) -> iced::Subscription<Self::Message> {
@@ -352,10 +431,30 @@ impl Application for SilkPlayer {
         // 把系统事件转给 EventOccurred 消息进行处理
         let map = event::listen().map(Message::EventOccurred);
 
-        Subscription::batch([
-            every, //press, release,
-            map,
-        ])
+        // 播放期间或有未处理的音频事件时，快速取出事件
+        let audio_tick = (self.audio.is_play() || self.audio.has_events()).then(|| {
+            iced::time::every(std::time::Duration::from_secs_f32(AUDIO_TICK))
+                .map(Message::AudioTick)
+        });
+
+        // 播放详情页显示频谱时高频刷新，暂停后等柱条回落完再停
+        let visualize = (self.setting.visualizer != VisualStyle::Off
+            && self.status == Status::PlayDetial
+            && (self.audio.is_play() || self.visualizer.is_active()))
+        .then(|| {
+            iced::time::every(std::time::Duration::from_secs_f32(visualizer::FRAME_SECS))
+                .map(Message::Visualize)
+        });
+
+        Subscription::batch(
+            [
+                every, //press, release,
+                map,
+            ]
+            .into_iter()
+            .chain(audio_tick)
+            .chain(visualize),
+        )
     }
 }
 
@@ -371,25 +470,10 @@ impl SilkPlayer {
         // }
         self.init_album_img(self.music_list.page_list.to_vec());
 
-        if !self.current_song.is_none() && self.audio.is_play() {
-            if !self.app_control.change_current_duration {
-                self.app_control.current_duration = self.audio.position();
-            }
-            if self.audio.position() > 0.0 {
-                // per 10s save once
-                if (self.audio.position() * 1000.0) as u64 / 1000 % 10 == 0 {
-                    self.save_play_status();
-                }
-            }
-
-            // 自动下一曲
-            if self.audio.is_play()
-                && (self.audio.is_over() || self.audio.position().ceil() >= self.audio.duration())
-            {
-                self.next_song();
-            }
-        }
-
+        self.check_sleep_timer();
+        self.check_output_device();
+        self.init_waveform();
+        self.toasts.expire();
         // 播放条自动隐藏处理
         if let Some(time) = self.app_control.hide_status_seconds {
             if time.elapsed().as_secs() > 5 {
@@ -446,11 +530,13 @@ impl SilkPlayer {
 
         self.scroll_lyric()
     }
+}
 
+impl<E: PlayEngine> SilkPlayer<E> {
     /// 缓存专辑封面
     fn init_album_img(&mut self, vec: Vec<MusicInfo>) {
         let init = |music_info: &MusicInfo| {
-            if let Ok(tag) = music_tag::audio::MusicTag::read_from_path(&music_info.path) {
+            if let Ok(tag) = util::read_music_tag(&music_info.path) {
                 util::log(format!("album : {:?}", tag.title()));
                 if let Some(artwork) = tag.artwork() {
                     let buf = artwork.data.to_vec();
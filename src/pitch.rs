@@ -0,0 +1,102 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use kira::clock::clock_info::ClockInfoProvider;
+use kira::effect::{Effect, EffectBuilder};
+use kira::modulator::value_provider::ModulatorValueProvider;
+use kira::Frame;
+
+const WINDOW_SECS: f32 = 0.05; // 每个颗粒的长度，越长音质越平滑但延迟越大
+
+/// 变调效果器，变速播放时按倒数变调即可保持原有音高
+pub struct PitchShiftBuilder;
+
+#[derive(Clone)]
+pub struct PitchShiftHandle {
+    ratio: Arc<AtomicU32>,
+}
+impl PitchShiftHandle {
+    /// 设置音高倍率，1.0 为不变调
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio.store(ratio.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl EffectBuilder for PitchShiftBuilder {
+    type Handle = PitchShiftHandle;
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        let ratio = Arc::new(AtomicU32::new(1f32.to_bits()));
+        (
+            Box::new(PitchShift {
+                ratio: ratio.clone(),
+                buffer: vec![],
+                write: 0,
+                window: 0.,
+                phase: 0.,
+            }),
+            PitchShiftHandle { ratio },
+        )
+    }
+}
+
+/// 双读指针的延迟线变调：两个读指针以不同于写入的速度移动，
+/// 相差半个窗口并交叉淡化，掩盖读指针回绕时的不连续
+struct PitchShift {
+    ratio: Arc<AtomicU32>,
+    buffer: Vec<Frame>,
+    write: usize,
+    window: f32, // 窗口长度，单位：帧
+    phase: f32,  // 0~1
+}
+
+impl PitchShift {
+    fn read(&self, delay: f32) -> Frame {
+        let len = self.buffer.len();
+        let pos = self.write as f32 - delay;
+        let pos = if pos < 0. { pos + len as f32 } else { pos };
+        let index = pos.floor() as usize % len;
+        let fraction = pos - pos.floor();
+        let a = self.buffer[index];
+        let b = self.buffer[(index + 1) % len];
+        a + (b - a) * fraction
+    }
+}
+
+impl Effect for PitchShift {
+    fn init(&mut self, sample_rate: u32) {
+        self.on_change_sample_rate(sample_rate);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.window = (sample_rate as f32 * WINDOW_SECS).max(16.);
+        self.buffer = vec![Frame::ZERO; self.window as usize + 4];
+        self.write = 0;
+        self.phase = 0.;
+    }
+
+    fn process(
+        &mut self,
+        input: Frame,
+        _dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        if self.buffer.is_empty() {
+            return input;
+        }
+        self.buffer[self.write] = input;
+        let ratio = f32::from_bits(self.ratio.load(Ordering::Relaxed));
+        let output = if (ratio - 1.).abs() < 0.001 {
+            input
+        } else {
+            self.phase = (self.phase + (1. - ratio) / self.window).rem_euclid(1.);
+            let other = (self.phase + 0.5) % 1.;
+            let gain = |phase: f32| (std::f32::consts::PI * phase).sin();
+            self.read(1. + self.phase * self.window) * gain(self.phase)
+                + self.read(1. + other * self.window) * gain(other)
+        };
+        self.write = (self.write + 1) % self.buffer.len();
+        output
+    }
+}
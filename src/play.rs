@@ -1,48 +1,136 @@
 use std::{collections::HashMap, time::Instant, vec};
 
 use iced::{
-    multi_window::Application,
     widget::scrollable::{self, RelativeOffset},
     window, Color, Command,
 };
-use music_tag::audio::MusicTag;
-use rand::Rng;
+use music_tag::{audio::MusicTag, lyrics::Lyrics};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    util::{self, get_str_value}, Message, SilkPlayer, Tab, PLAY_LIST_SCROLLABLE_ID
+    audio::{self, AudioEvent},
+    chapter::{self, Chapter},
+    engine::PlayEngine,
+    list_edit::ListSelection,
+    lyric::{self, LyricPriority},
+    shuffle::{ShuffleOrder, ShuffleTrack},
+    util::{self, get_str_value},
+    waveform, Message, SilkPlayer, Tab, PLAY_LIST_SCROLLABLE_ID
 };
 
+const PRELOAD_SECS: f32 = 5.0; // 距离结束多少秒时预加载下一首
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 2.0;
+pub const SPEED_STEP: f32 = 0.05;
+const MIN_LOOP_SECS: f32 = 0.5; // A-B 循环的最短区间
+const DEVICE_CHECK_SECS: u64 = 10; // 检查选定的输出设备是否可用的间隔
+const CHAPTER_RESTART_SECS: f32 = 3.0; // 章节播放超过多少秒时，上一章先回到本章开头
+const RESUME_MIN_SECS: f32 = 10.0; // 播放超过多少秒才记录续播位置
+const RESUME_SAVE_SECS: f32 = 5.0; // 续播位置变化超过多少秒才保存
+const RESUME_END_SECS: f32 = 15.0; // 距离结尾多少秒以内视为已播完
+const STATUS_SAVE_SECS: u64 = 10; // 播放期间保存播放状态的间隔
+const MAX_ERROR_SKIPS: u32 = 5; // 连续跳过无法播放的歌曲的上限，避免在一串坏文件间反复尝试
+
+/// 播放顺序，是否循环由 Repeat 单独设置
 #[derive(Default, PartialEq, Serialize, Deserialize)]
 pub enum PlayMode {
-    Single,
     #[default]
-    Cycle,
-    Random,
+    // 兼容旧版配置，Single 读取后另设单曲循环，见 config::migrate_play_mode
+    #[serde(alias = "Single", alias = "Cycle")]
+    Sequence, // 按列表顺序
+    Random,        // 逐首随机
+    ShuffleAlbum,  // 专辑顺序随机，专辑内按列表顺序
+    ShuffleFolder, // 文件夹顺序随机，文件夹内按列表顺序
 }
 impl PlayMode {
     pub fn name(&self) -> String {
         match self {
-            PlayMode::Single => "单曲循环",
-            PlayMode::Cycle => "列表循环",
+            PlayMode::Sequence => "顺序播放",
             PlayMode::Random => "随机播放",
+            PlayMode::ShuffleAlbum => "专辑随机",
+            PlayMode::ShuffleFolder => "文件夹随机",
         }
         .to_string()
     }
     pub fn icon(&self) -> String {
         match self {
-            PlayMode::Single => "cycle_single",
-            PlayMode::Cycle => "cycle_list",
-            PlayMode::Random => "cycle_random",
+            PlayMode::Sequence => "play_list",
+            PlayMode::Random | PlayMode::ShuffleAlbum | PlayMode::ShuffleFolder => "cycle_random",
         }
         .to_string()
     }
+    /// 分组随机与逐首随机共用图标，图标旁显示分组名称以便区分
+    pub fn badge(&self) -> Option<&'static str> {
+        match self {
+            PlayMode::ShuffleAlbum => Some("专辑"),
+            PlayMode::ShuffleFolder => Some("文件夹"),
+            PlayMode::Sequence | PlayMode::Random => None,
+        }
+    }
 
     pub fn next(&self) -> Self {
         match self {
-            PlayMode::Single => PlayMode::Cycle,
-            PlayMode::Cycle => PlayMode::Random,
-            PlayMode::Random => PlayMode::Single,
+            PlayMode::Sequence => PlayMode::Random,
+            PlayMode::Random => PlayMode::ShuffleAlbum,
+            PlayMode::ShuffleAlbum => PlayMode::ShuffleFolder,
+            PlayMode::ShuffleFolder => PlayMode::Sequence,
+        }
+    }
+
+    /// 是否按随机顺序播放
+    pub fn is_shuffle(&self) -> bool {
+        *self != PlayMode::Sequence
+    }
+
+    /// 随机播放时歌曲所属的分组，同组歌曲连续播放
+    fn shuffle_group(&self, music_info: &MusicInfo) -> String {
+        let folder = || {
+            std::path::Path::new(&music_info.path)
+                .parent()
+                .map(|parent| parent.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        match self {
+            PlayMode::ShuffleAlbum if !music_info.album.is_empty() => {
+                format!("{}\n{}", music_info.album, music_info.artist)
+            }
+            PlayMode::ShuffleAlbum | PlayMode::ShuffleFolder => folder(),
+            _ => music_info.path.to_string(),
+        }
+    }
+}
+
+/// 循环方式
+#[derive(Default, PartialEq, Serialize, Deserialize)]
+pub enum Repeat {
+    Off, // 播完一遍后停止
+    One, // 单曲循环
+    #[default]
+    All, // 列表循环
+}
+impl Repeat {
+    pub fn name(&self) -> String {
+        match self {
+            Repeat::Off => "不循环",
+            Repeat::One => "单曲循环",
+            Repeat::All => "列表循环",
+        }
+        .to_string()
+    }
+    pub fn icon(&self) -> String {
+        match self {
+            Repeat::Off => "go",
+            Repeat::One => "cycle_single",
+            Repeat::All => "cycle_list",
+        }
+        .to_string()
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Repeat::Off => Repeat::All,
+            Repeat::All => Repeat::One,
+            Repeat::One => Repeat::Off,
         }
     }
 }
@@ -56,11 +144,26 @@ pub enum SongControl {
     SnapToCurrentSong, // 定位当前播放的歌曲
     RandomSelect,      // 随机选取n首歌开始播放
     First(MusicInfo),
-    Next(MusicInfo),
+    Next(MusicInfo),  // 下一首播放，插到待播队列最前面
+    Queue(MusicInfo), // 加入待播队列末尾
+    Dequeue(usize),   // 从待播队列移除
+    ClearQueue,
     Last(MusicInfo),
     List(MusicInfo),
     Remove(MusicInfo),
     PlayOrPause,
+    AbLoop(AbLoop),
+    Chapter(bool), // 上一章/下一章，没有章节时切换歌曲
+    SeekTo(f32),
+}
+
+#[derive(Debug, Clone)]
+pub enum AbLoop {
+    A,      // 设置 A 点
+    B,      // 设置 B 点并开始循环
+    Toggle, // 依次设置 A 点、B 点、清除
+    Clear,
+    Save, // 保存当前区间到歌曲，没有区间时删除已保存的
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -91,7 +194,9 @@ pub struct MusicInfo {
     pub file_name: String,
     pub path: String,
     pub lyric: Vec<ShowLyric>,
+    pub lyric_file: Option<String>, // 正在使用的外部 .lrc 歌词，为空时为内嵌歌词
     pub tags: Vec<Tag>,
+    pub chapters: Vec<Chapter>, // 开始播放时读取
 }
 impl Default for MusicInfo {
     fn default() -> Self {
@@ -106,8 +211,10 @@ impl Default for MusicInfo {
             file_name: Default::default(),
             path: Default::default(),
             lyric: Default::default(),
+            lyric_file: Default::default(),
             fmt: Default::default(),
             tags: Default::default(),
+            chapters: Default::default(),
         }
     }
 }
@@ -122,7 +229,7 @@ impl MusicInfo {
             return MusicInfo::default();
         }
 
-        match music_tag::audio::MusicTag::read_from_path(path) {
+        match util::read_music_tag(path) {
             Err(err) => {
                 util::log_err(format!("read tag err path={} err={}", path, err));
                 MusicInfo::default()
@@ -156,8 +263,10 @@ impl MusicInfo {
                     file_name: get_str_value(tag.year(), path),
                     path: path.to_string(),
                     lyric,
+                    lyric_file: None,
                     fmt: MusicFormat::from(tag.fmt()),
                     tags,
+                    chapters: vec![],
                 }
             }
         }
@@ -211,6 +320,20 @@ pub struct AppControl {
     pub press_left_mouse_key: bool,
     pub desktop_lyric_win_id: Option<window::Id>,
     pub history_list: Vec<String>, // 播放历史
+    pub next_song: Option<MusicInfo>, // 已预加载的下一首
+    pub eq_preset_name: String,       // 均衡器保存预设时输入的名称
+    pub bookmark_name: String,        // 添加书签时输入的名称
+    pub loop_a: Option<f32>,          // A-B 循环已设置的 A 点
+    pub output_devices: Vec<String>,  // 可用的输出设备
+    pub device_checked: Option<Instant>,
+    pub shuffle: ShuffleOrder, // 随机播放顺序
+    pub up_next: Vec<MusicInfo>, // 待播队列，优先于播放列表的顺序
+    pub list_anchor: Option<String>, // 最近播放的列表歌曲，播完待播队列后从这里继续
+    pub list_selection: ListSelection, // 播放列表中选中的歌曲
+    pub status_saved: Option<Instant>, // 播放期间上次保存播放状态的时间
+    pub error_skips: u32, // 已连续跳过的无法播放的歌曲数
+    pub waveform: Option<(String, Vec<f32>)>, // 当前歌曲的波形，(路径, 峰值)
+    pub waveform_pending: Option<String>, // 已提交生成波形的歌曲
 }
 impl Default for AppControl {
     fn default() -> Self {
@@ -227,6 +350,20 @@ impl Default for AppControl {
             press_left_mouse_key: false,
             desktop_lyric_win_id: None,
             history_list: vec![],
+            next_song: None,
+            eq_preset_name: String::new(),
+            bookmark_name: String::new(),
+            loop_a: None,
+            output_devices: vec![],
+            device_checked: None,
+            shuffle: Default::default(),
+            up_next: vec![],
+            list_anchor: None,
+            list_selection: Default::default(),
+            status_saved: None,
+            error_skips: 0,
+            waveform: None,
+            waveform_pending: None,
         }
     }
 }
@@ -244,9 +381,13 @@ pub struct ShowLyric {
 }
 impl ShowLyric {
     pub fn build(music_tag: &MusicTag) -> Vec<ShowLyric> {
+        Self::parse(music_tag.lyrics())
+    }
+
+    pub fn parse(lyrics: Option<Lyrics>) -> Vec<ShowLyric> {
         let mut tmp_lyric_list = vec![];
 
-        if let Some(lyric_value) = music_tag.lyrics() {
+        if let Some(lyric_value) = lyrics {
             // if !lyric_value.lines().is_empty() {
             //     println!("{:?} = {:?}", music_tag.title(), lyric_value.lines()[lyric_value.lines().len() - 1]);
             // }
@@ -289,12 +430,15 @@ impl ShowLyric {
     }
 }
 
-impl SilkPlayer {
+impl<E: PlayEngine> SilkPlayer<E> {
     pub fn clear_play(&mut self) {
         self.play_list.clear();
         self.audio.stop();
         self.current_song = MusicInfo::default();
         self.app_control.history_list.clear();
+        self.app_control.shuffle.clear();
+        self.app_control.up_next.clear();
+        self.app_control.list_anchor = None;
         self.save_play_status();
     }
 
@@ -312,6 +456,68 @@ impl SilkPlayer {
         self.audio.pause();
     }
 
+    /// 读取当前歌曲的波形，没有缓存时提交到线程池生成
+    pub fn init_waveform(&mut self) {
+        let path = &self.current_song.path;
+        let loaded = self
+            .app_control
+            .waveform
+            .as_ref()
+            .is_some_and(|(waveform_path, _)| waveform_path == path);
+        if path.is_empty() || loaded {
+            return;
+        }
+        if let Some(peaks) = waveform::cached(path) {
+            self.app_control.waveform = Some((path.to_string(), peaks));
+            return;
+        }
+        let duration = self.audio.duration();
+        if self.app_control.waveform_pending.as_ref() == Some(path) || duration <= 0. {
+            return;
+        }
+        self.app_control.waveform_pending = Some(path.to_string());
+        let path = path.to_string();
+        self.thread_pool
+            .execute(move || waveform::generate(&path, duration));
+    }
+
+    /// 按设置的优先级选择内嵌歌词或外部 .lrc 歌词，开始播放和修改歌词设置时调用
+    pub fn load_lyrics(&mut self) {
+        let song = &self.current_song;
+        if song.is_none() {
+            return;
+        }
+        // 之前换成了外部歌词时重新读取内嵌歌词
+        let embedded = || match song.lyric_file {
+            None => song.lyric.clone(),
+            Some(_) => match util::read_music_tag(&song.path) {
+                Ok(tag) => ShowLyric::build(&tag),
+                Err(_) => vec![],
+            },
+        };
+        let external = || {
+            let file = lyric::find(&song.path, &song.artist, &song.title, &self.setting.lyric_dir)?;
+            let lyrics = ShowLyric::parse(lyric::read(&file).map(Lyrics::from));
+            util::log(format!("load lrc {:?}", file));
+            (!lyrics.is_empty()).then(|| (lyrics, Some(file.to_string_lossy().to_string())))
+        };
+
+        let (lyrics, lyric_file) = match self.setting.lyric_priority {
+            LyricPriority::EmbeddedOnly => (embedded(), None),
+            LyricPriority::EmbeddedFirst => {
+                let lyrics = embedded();
+                match lyrics.is_empty() {
+                    true => external().unwrap_or((lyrics, None)),
+                    false => (lyrics, None),
+                }
+            }
+            LyricPriority::ExternalFirst => external().unwrap_or_else(|| (embedded(), None)),
+        };
+        self.current_song.lyric = lyrics;
+        self.current_song.lyric_file = lyric_file;
+        self.app_control.current_lyric_index = 0;
+    }
+
     /// 尝试读取专辑页面的图片信息
     pub fn init_album_color(&mut self) {
         if self.current_song.album_color.is_empty() {
@@ -369,11 +575,7 @@ impl SilkPlayer {
                 (true, false)
             }
             SongControl::PlayNext(next) => {
-                if next {
-                    self.next_song();
-                } else {
-                    self.pre_song();
-                }
+                self.skip_song(next);
                 (false, false)
             }
             SongControl::PlayClear => {
@@ -387,7 +589,7 @@ impl SilkPlayer {
                     false
                 };
                 if exist {
-                    let _ = self.update(Message::SongControl(SongControl::List(music_info)));
+                    let _ = self.change_play_list(SongControl::List(music_info));
                     (false, false)
                 } else {
                     if let Ok(mut all_list) = self.play_list.all_list.try_lock() {
@@ -398,26 +600,46 @@ impl SilkPlayer {
                 }
             }
             SongControl::Next(music_info) => {
-                let exist = if let Ok(all_list) = self.play_list.all_list.try_lock() {
-                    all_list.contains(&music_info)
-                } else {
-                    false
-                };
-                if exist {
-                    (false, false)
-                } else {
-                    let is_empty = if let Ok(all_list) = self.play_list.all_list.try_lock() {
-                        all_list.is_empty()
-                    } else {
-                        true
-                    };
-                    let index = if is_empty { 0 } else { 1 };
-                    if let Ok(mut all_list) = self.play_list.all_list.try_lock() {
-                        all_list.insert(index, music_info.clone());
-                    }
-                    self.current_song = music_info;
-                    (true, is_empty)
+                if all_list_empty {
+                    return self.change_play_list(SongControl::First(music_info));
                 }
+                self.app_control
+                    .up_next
+                    .retain(|item| item.path != music_info.path);
+                self.app_control.up_next.insert(0, music_info);
+                self.reset_next_song();
+                self.save_play_status();
+                (false, false)
+            }
+            SongControl::Queue(music_info) => {
+                if all_list_empty {
+                    return self.change_play_list(SongControl::First(music_info));
+                }
+                if !self
+                    .app_control
+                    .up_next
+                    .iter()
+                    .any(|item| item.path == music_info.path)
+                {
+                    self.app_control.up_next.push(music_info);
+                    self.reset_next_song();
+                    self.save_play_status();
+                }
+                (false, false)
+            }
+            SongControl::Dequeue(index) => {
+                if index < self.app_control.up_next.len() {
+                    self.app_control.up_next.remove(index);
+                    self.reset_next_song();
+                    self.save_play_status();
+                }
+                (false, false)
+            }
+            SongControl::ClearQueue => {
+                self.app_control.up_next.clear();
+                self.reset_next_song();
+                self.save_play_status();
+                (false, false)
             }
             SongControl::Last(music_info) => {
                 let exist = if let Ok(all_list) = self.play_list.all_list.try_lock() {
@@ -514,8 +736,21 @@ impl SilkPlayer {
                 self.audio.toggle_play();
                 (false, false)
             }
+            SongControl::AbLoop(control) => {
+                self.ab_loop(control);
+                (false, false)
+            }
+            SongControl::Chapter(next) => {
+                self.skip_chapter(next);
+                (false, false)
+            }
+            SongControl::SeekTo(position) => {
+                self.audio.seek(position);
+                (false, false)
+            }
         };
         if filter {
+            self.reset_next_song();
             self.play_list.filter();
         }
         let all_list_empty = if let Ok(all_list) = self.play_list.all_list.try_lock() {
@@ -530,9 +765,75 @@ impl SilkPlayer {
         Command::none()
     }
 
-    pub fn pre_song(&mut self) {
+    /// 播放列表中全部歌曲的路径及随机播放的分组
+    fn shuffle_tracks(&self) -> Vec<ShuffleTrack> {
+        match self.play_list.all_list.try_lock() {
+            Ok(all_list) => all_list
+                .iter()
+                .map(|item| {
+                    let group = self.setting.play_mode.shuffle_group(item);
+                    (item.path.to_string(), group)
+                })
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// 按当前播放模式重新生成随机顺序
+    pub fn rebuild_shuffle(&mut self) {
+        let tracks = self.shuffle_tracks();
+        let current = self.current_song.path.to_string();
+        self.app_control.shuffle.rebuild(&tracks, &current);
+    }
+
+    fn in_play_list(&self, path: &str) -> bool {
+        self.find_in_play_list(path).is_some()
+    }
+
+    fn find_in_play_list(&self, path: &str) -> Option<MusicInfo> {
+        let all_list = self.play_list.all_list.try_lock().ok()?;
+        all_list.iter().find(|item| item.path == path).cloned()
+    }
+
+    /// 播放列表按顺序继续时的当前位置，正在播放待播队列中的歌曲时为之前播放的列表歌曲
+    fn list_current_path(&self) -> String {
+        if !self.in_play_list(&self.current_song.path) {
+            if let Some(ref anchor) = self.app_control.list_anchor {
+                return anchor.to_string();
+            }
+        }
+        self.current_song.path.to_string()
+    }
+
+    /// 按随机播放顺序选出歌曲
+    fn pick_shuffle_song(&mut self, next: bool, wrap: bool) -> Option<MusicInfo> {
+        let tracks = self.shuffle_tracks();
+        let current = self.list_current_path();
+        let path = if next {
+            self.app_control.shuffle.peek_next(&tracks, &current, wrap)?
+        } else {
+            self.app_control.shuffle.peek_prev(&tracks, &current)?
+        };
+        self.find_in_play_list(&path)
+    }
+
+    /// 列表中的上一首，随机播放时沿随机顺序回退
+    pub fn pick_pre_song(&mut self) -> Option<MusicInfo> {
         if self.play_list.page_list.is_empty() {
-            return;
+            return None;
+        }
+        // 正在播放待播队列中的歌曲，回到之前播放的列表歌曲
+        if !self.in_play_list(&self.current_song.path) {
+            if let Some(anchor) = self.app_control.list_anchor.clone() {
+                if let Some(item) = self.find_in_play_list(&anchor) {
+                    return Some(item);
+                }
+            }
+        }
+        if self.setting.play_mode.is_shuffle() {
+            if let Some(item) = self.pick_shuffle_song(false, true) {
+                return Some(item);
+            }
         }
         let mut index = 0;
 
@@ -546,107 +847,230 @@ impl SilkPlayer {
             }
             all_list_len = all_list.len();
         }
+        if all_list_len == 0 {
+            return None;
+        }
 
         index = (index + all_list_len - 1) % all_list_len;
         if let Ok(all_list) = self.play_list.all_list.try_lock() {
-            self.current_song = all_list[index].clone();
+            return Some(all_list[index].clone());
         }
-        self.start_play();
+        None
     }
 
+    /// 手动切换上一首/下一首，按设置交叉淡入淡出
+    pub fn skip_song(&mut self, next: bool) {
+        let item = if next {
+            self.pick_next_song(true)
+        } else {
+            self.pick_pre_song()
+        };
+        if let Some(item) = item {
+            let fade = self.crossfade_secs(&item, true);
+            self.current_song = item;
+            self.start_play_fade(fade);
+        }
+    }
+
+    /// 切换到指定歌曲时的交叉淡入淡出时长，同一专辑的连续曲目不做交叉淡入淡出
+    fn crossfade_secs(&self, next: &MusicInfo, manual: bool) -> f32 {
+        let same_album = !next.album.is_empty()
+            && next.album == self.current_song.album
+            && next.path != self.current_song.path;
+        if same_album {
+            return 0.;
+        }
+        if manual {
+            self.setting.crossfade_manual
+        } else {
+            self.setting.crossfade
+        }
+    }
+
+    /// 自动切到下一首，不循环时播完最后一首停在开头
     pub fn next_song(&mut self) {
+        match self.pick_next_song(false) {
+            Some(item) => {
+                self.current_song = item;
+                self.start_play();
+            }
+            None if !self.current_song.path.is_empty() => {
+                util::log("play list finished");
+                self.audio.load(&self.current_song.path, false);
+                self.save_play_status();
+            }
+            None => {}
+        }
+    }
+
+    /// 按播放模式选出下一首，不会改变当前播放
+    ///
+    /// manual 为手动切歌，单曲循环时也切到下一首，不循环时在列表末尾回到开头
+    pub fn pick_next_song(&mut self, manual: bool) -> Option<MusicInfo> {
         let all_list_empty = if let Ok(all_list) = self.play_list.all_list.try_lock() {
             all_list.is_empty()
         } else {
             true
         };
         if all_list_empty {
-            return;
+            return None;
+        }
+
+        if !manual && self.setting.repeat == Repeat::One {
+            return Some(self.current_song.clone());
+        }
+        if let Some(item) = self.app_control.up_next.first() {
+            return Some(item.clone());
+        }
+        let wrap = manual || self.setting.repeat == Repeat::All;
+
+        if self.setting.play_mode.is_shuffle() {
+            return self
+                .pick_shuffle_song(true, wrap)
+                .or_else(|| wrap.then(|| self.current_song.clone()));
+        }
+
+        let current = self.list_current_path();
+        let all_list = self.play_list.all_list.try_lock().ok()?;
+        if current.is_empty() {
+            return all_list.first().cloned();
+        }
+        let index = all_list.iter().position(|item| item.path == current);
+        match index {
+            Some(index) if index + 1 < all_list.len() => Some(all_list[index + 1].clone()),
+            Some(_) if !wrap => None,
+            _ => all_list.first().cloned(),
+        }
+    }
+
+    /// 取出音频事件依次处理
+    pub fn poll_audio(&mut self) {
+        for event in self.audio.poll_events() {
+            self.audio_event(event);
         }
+    }
 
-        let index_plus = |path: String, list: &Vec<MusicInfo>| {
-            let mut hit = false || self.current_song.path.is_empty();
-            if list.is_empty() {
-                return None;
+    /// 处理音频事件：无缝衔接的下一首开始、播放结束自动切歌、播放期间定期保存进度
+    pub fn audio_event(&mut self, event: AudioEvent) {
+        match event {
+            AudioEvent::Started(path) => {
+                // 曾经无法播放的文件已能正常打开
+                if self
+                    .track_store
+                    .get(&path)
+                    .is_some_and(|record| record.unplayable.is_some())
+                {
+                    self.track_store
+                        .update(&path, |record| record.unplayable = None);
+                }
+                // 只需处理无缝衔接切换的下一首，其余在开始播放时已处理
+                let gapless = self
+                    .app_control
+                    .next_song
+                    .as_ref()
+                    .is_some_and(|next| next.path == path);
+                if let Some(next) = gapless.then(|| self.app_control.next_song.take()).flatten() {
+                    util::log(format!("gapless start {:?}", next.title));
+                    self.save_resume_position(true);
+                    self.sleep_track_finished();
+                    self.current_song = next;
+                    self.song_started();
+                }
             }
-            for item in list {
-                if hit {
-                    return Some(item.clone());
+            AudioEvent::Ended(path) => {
+                if path != self.current_song.path {
+                    return;
                 }
-                if path == item.path {
-                    hit = true;
+                // 定时关闭设为播完当前歌曲时在这里暂停
+                self.check_sleep_timer();
+                if self.audio.is_play() {
+                    self.save_resume_position(true);
+                    self.sleep_track_finished();
+                    self.next_song();
                 }
             }
-            if hit {
-                Some(list[0].clone())
-            } else {
-                None
+            AudioEvent::Error(path, err, playing) => {
+                util::log_err(format!("audio error path={} err={}", path, err));
+                self.toasts
+                    .push(format!("无法播放 {}：{}", path, err));
+                self.track_store
+                    .update(&path, |record| record.unplayable = Some(err));
+                if playing && path == self.current_song.path {
+                    self.skip_unplayable();
+                }
             }
-        };
-
-        match self.setting.play_mode {
-            PlayMode::Single => {}
-            PlayMode::Cycle => {
-                if let Ok(all_list) = self.play_list.all_list.try_lock() {
-                    if let Some(item) = index_plus(self.current_song.path.to_string(), &all_list) {
-                        self.current_song = item;
-                    }
+            AudioEvent::Position(position) => {
+                self.app_control.error_skips = 0;
+                if !self.app_control.change_current_duration {
+                    self.app_control.current_duration = position;
+                }
+                self.save_resume_position(false);
+                let save_status = self
+                    .app_control
+                    .status_saved
+                    .is_none_or(|saved| saved.elapsed().as_secs() >= STATUS_SAVE_SECS);
+                if save_status {
+                    self.app_control.status_saved = Some(Instant::now());
+                    self.save_play_status();
                 }
+                self.gapless_next();
             }
-            PlayMode::Random => {
-                let mut tmp: usize = rand::thread_rng().gen();
-                let mut loop_cnt = 0;
-
-                let all_list_len = if let Ok(all_list) = self.play_list.all_list.try_lock() {
-                    all_list.len()
-                } else {
-                    0
-                };
-
-                loop {
-                    let index = tmp % all_list_len;
-
-                    let music_info = if let Ok(all_list) = self.play_list.all_list.try_lock() {
-                        Some(all_list[index].clone())
-                    } else {
-                        None
-                    };
-
-                    if music_info.is_none() {
-                        continue;
-                    }
-                    let music_info = music_info.unwrap();
-                    // 随机到同一首就继续生成下一个随机
-                    if music_info.path != self.current_song.path
-                        && !self.app_control.history_list.contains(&music_info.path)
-                    {
-                        self.current_song = music_info.clone();
-                        break;
-                    }
+        }
+    }
 
-                    if self.app_control.history_list.len() == all_list_len {
-                        self.app_control.history_list.clear();
-                    }
+    /// 当前歌曲无法播放，自动切到下一首，连续失败达到上限时停止
+    fn skip_unplayable(&mut self) {
+        if self.app_control.error_skips >= MAX_ERROR_SKIPS {
+            util::log_err("too many unplayable files, stop");
+            self.toasts.push(format!(
+                "连续 {} 首歌曲无法播放，已停止",
+                self.app_control.error_skips + 1
+            ));
+            self.app_control.error_skips = 0;
+            self.audio.stop();
+            return;
+        }
+        self.app_control.error_skips += 1;
+        // 单曲循环时也要切走，列表播完时停止而不是重新打开这首
+        let item = self.pick_next_song(self.setting.repeat == Repeat::One);
+        match item {
+            Some(item) if item.path != self.current_song.path => {
+                self.current_song = item;
+                self.start_play();
+            }
+            _ => self.audio.stop(),
+        }
+    }
 
-                    tmp = rand::thread_rng().gen();
-                    loop_cnt += 1;
-                    if loop_cnt > 10 {
-                        if let Ok(all_list) = self.play_list.all_list.try_lock() {
-                            if let Some(item) =
-                                index_plus(self.current_song.path.to_string(), &all_list)
-                            {
-                                self.current_song = item;
-                            }
-                        }
-                        break; // 保护，随机过多次
-                    }
+    /// 临近结尾时预加载下一首，到点后由音频无缝衔接
+    pub fn gapless_next(&mut self) {
+        if self.audio.is_play()
+            && self.audio.next_path().is_none()
+            && self.audio.loop_region().is_none()
+            && !self.sleep_timer.stop_at_track_end()
+            && self.audio.duration() - self.audio.position() <= PRELOAD_SECS + self.setting.crossfade
+        {
+            if let Some(next) = self.pick_next_song(false) {
+                let crossfade = self.crossfade_secs(&next, false);
+                if self.audio.queue_next(&next.path, crossfade) {
+                    self.app_control.next_song = Some(next);
                 }
             }
         }
-        self.start_play();
+    }
+
+    /// 播放列表或播放模式变化后，已预加载的下一首作废
+    pub fn reset_next_song(&mut self) {
+        self.audio.clear_next();
+        self.app_control.next_song = None;
     }
 
     pub fn start_play(&mut self) {
+        self.start_play_fade(0.);
+    }
+
+    /// 开始播放当前歌曲，fade 大于 0 时与正在播放的歌曲交叉淡入淡出
+    pub fn start_play_fade(&mut self, fade: f32) {
         let all_list_empty = if let Ok(all_list) = self.play_list.all_list.try_lock() {
             all_list.is_empty()
         } else {
@@ -665,11 +1089,20 @@ impl SilkPlayer {
 
         let music_info = &self.current_song;
         util::log(format!("now start {:?}", music_info.title));
-        self.audio.start_play(&music_info.path, true);
-        //
+        self.app_control.next_song = None;
+        // 打开失败时由 Error 事件跳过，不更新播放记录
+        if self.audio.cross_play(&music_info.path, fade) {
+            self.song_started();
+        }
+    }
+
+    /// 歌曲开始播放后刷新界面相关的状态
+    fn song_started(&mut self) {
         self.app_control.current_lyric_index = 0;
 
         self.current_song.time = self.audio.duration();
+        self.current_song.chapters = chapter::read(&self.current_song.path);
+        self.load_lyrics();
 
         self.init_album_img(vec![self.current_song.clone()]);
         self.init_album_color();
@@ -678,6 +1111,551 @@ impl SilkPlayer {
         self.app_control
             .history_list
             .push(self.current_song.path.to_string());
+        // 待播队列中的歌曲播放后移出队列
+        let path = self.current_song.path.to_string();
+        if let Some(index) = self.app_control.up_next.iter().position(|item| item.path == path) {
+            self.app_control.up_next.remove(index);
+        }
+        let in_list = self.in_play_list(&path);
+        if in_list {
+            self.app_control.list_anchor = Some(path.to_string());
+        }
+        if in_list && self.setting.play_mode.is_shuffle() {
+            let shuffle = &self.app_control.shuffle;
+            // 按分组随机时直接选择了别处的歌曲，从这首所在的分组重新开始
+            if self.setting.play_mode != PlayMode::Random && !shuffle.is_adjacent(&path) {
+                self.rebuild_shuffle();
+            } else {
+                self.app_control.shuffle.played(&path);
+            }
+        }
         self.current_song.album_path = format!("{}/assets/default.png", util::current_dir());
+        self.apply_eq();
+        self.apply_track_speed();
+        self.apply_track_loop();
+        self.apply_resume_position();
+        self.sleep_song_started();
+    }
+
+    pub fn ab_loop(&mut self, control: AbLoop) {
+        let position = self.audio.position();
+        match control {
+            AbLoop::A => {
+                self.audio.set_loop_region(None);
+                self.app_control.loop_a = Some(position);
+            }
+            AbLoop::B => {
+                if let Some(a) = self.app_control.loop_a {
+                    if position >= a + MIN_LOOP_SECS {
+                        self.audio.set_loop_region(Some((a, position)));
+                        self.reset_next_song();
+                    }
+                }
+            }
+            AbLoop::Toggle => {
+                let next = match (self.app_control.loop_a, self.audio.loop_region()) {
+                    (_, Some(_)) => AbLoop::Clear,
+                    (Some(_), None) => AbLoop::B,
+                    (None, None) => AbLoop::A,
+                };
+                self.ab_loop(next);
+            }
+            AbLoop::Clear => {
+                self.app_control.loop_a = None;
+                self.audio.set_loop_region(None);
+            }
+            AbLoop::Save => {
+                let path = self.current_song.path.to_string();
+                let region = self.audio.loop_region();
+                self.track_store.update(&path, |record| record.ab_loop = region);
+            }
+        }
+    }
+
+    /// 恢复歌曲保存的 A-B 循环区间
+    fn apply_track_loop(&mut self) {
+        self.app_control.loop_a = None;
+        let region = self
+            .track_store
+            .get(&self.current_song.path)
+            .and_then(|record| record.ab_loop);
+        if let Some((a, b)) = region {
+            if b <= self.audio.duration() {
+                self.app_control.loop_a = Some(a);
+                self.audio.set_loop_region(Some((a, b)));
+            }
+        }
+    }
+
+    /// 调整播放速度，开启按歌曲记忆时同时保存
+    pub fn change_speed(&mut self, speed: f32) {
+        let speed = ((speed / SPEED_STEP).round() * SPEED_STEP).clamp(MIN_SPEED, MAX_SPEED);
+        self.audio.set_speed(speed, self.setting.keep_pitch);
+        self.reset_next_song();
+        if self.setting.remember_speed {
+            let path = self.current_song.path.to_string();
+            self.track_store.update(&path, |record| {
+                record.speed = if speed == 1.0 { None } else { Some(speed) };
+            });
+        }
+    }
+
+    /// 当前所在章节的序号
+    pub fn current_chapter(&self) -> Option<usize> {
+        let position = self.audio.position();
+        self.current_song
+            .chapters
+            .iter()
+            .rposition(|chapter| chapter.start <= position)
+    }
+
+    /// 在章节之间跳转，已在首尾章节时切换歌曲
+    ///
+    /// 当前章节已播放超过几秒时，上一章先回到本章开头
+    pub fn skip_chapter(&mut self, next: bool) {
+        let chapters = &self.current_song.chapters;
+        if chapters.is_empty() {
+            return self.skip_song(next);
+        }
+        let current = self.current_chapter();
+        let target = match (next, current) {
+            (true, Some(index)) => Some(index + 1).filter(|index| *index < chapters.len()),
+            (true, None) => Some(0),
+            (false, Some(index))
+                if self.audio.position() - chapters[index].start > CHAPTER_RESTART_SECS =>
+            {
+                Some(index)
+            }
+            (false, Some(index)) => index.checked_sub(1),
+            (false, None) => None,
+        };
+        match target {
+            Some(index) => {
+                let start = chapters[index].start;
+                self.audio.seek(start);
+            }
+            None => self.skip_song(next),
+        }
+    }
+
+    /// 长音频按歌曲记录续播位置：时长超过设置的阈值或位于指定目录
+    fn resume_enabled(&self, path: &str, duration: f32) -> bool {
+        if path.is_empty() {
+            return false;
+        }
+        let minutes = self.setting.resume_minutes;
+        (minutes > 0. && duration >= minutes * 60.)
+            || self
+                .setting
+                .resume_folders
+                .iter()
+                .any(|folder| std::path::Path::new(path).starts_with(folder))
+    }
+
+    /// 记录当前歌曲的续播位置，finished 为 true 表示已自然播完
+    pub fn save_resume_position(&mut self, finished: bool) {
+        let path = self.current_song.path.to_string();
+        let (position, duration) = (self.audio.position(), self.audio.duration());
+        if !self.resume_enabled(&path, duration) {
+            return;
+        }
+        let record = self.track_store.get(&path);
+        let saved = record.and_then(|record| record.resume);
+        let was_finished = record.is_some_and(|record| record.finished);
+        if finished || position >= duration - RESUME_END_SECS {
+            if !was_finished {
+                self.track_store.update(&path, |record| {
+                    record.resume = None;
+                    record.finished = true;
+                });
+            }
+        } else if position >= RESUME_MIN_SECS
+            && saved.is_none_or(|saved| (saved - position).abs() >= RESUME_SAVE_SECS)
+        {
+            self.track_store.update(&path, |record| {
+                record.resume = Some(position);
+                record.finished = false;
+            });
+        }
+    }
+
+    /// 打开长音频时跳回上次播放的位置
+    fn apply_resume_position(&mut self) {
+        if !self.resume_enabled(&self.current_song.path, self.audio.duration()) {
+            return;
+        }
+        let resume = self
+            .track_store
+            .get(&self.current_song.path)
+            .filter(|record| !record.finished)
+            .and_then(|record| record.resume);
+        if let Some(position) = resume {
+            util::log(format!("resume {} at {}", self.current_song.path, position));
+            self.audio.seek(position);
+        }
+    }
+
+    /// 歌曲行上显示的续播状态
+    pub fn resume_label(&self, path: &str) -> Option<String> {
+        let record = self.track_store.get(path)?;
+        if record.finished {
+            return Some("已播完".to_string());
+        }
+        record
+            .resume
+            .map(|position| format!("播放至 {}", util::play_time(position)))
+    }
+
+    /// 恢复歌曲记忆的播放速度，没有记录时恢复原速
+    fn apply_track_speed(&mut self) {
+        if !self.setting.remember_speed {
+            return;
+        }
+        let speed = self
+            .track_store
+            .get(&self.current_song.path)
+            .and_then(|record| record.speed)
+            .unwrap_or(1.0);
+        if speed != self.audio.speed() {
+            self.audio.set_speed(speed, self.setting.keep_pitch);
+        }
+    }
+}
+
+impl SilkPlayer {
+    /// 选定的输出设备消失时退回系统默认设备，重新出现后切换回去
+    ///
+    /// 没有可用的音频设备时定期重试初始化
+    pub fn check_output_device(&mut self) {
+        if self.setting.output_device.is_empty() && !self.audio.is_offline() {
+            return;
+        }
+        if let Some(time) = self.app_control.device_checked {
+            if time.elapsed().as_secs() < DEVICE_CHECK_SECS {
+                return;
+            }
+        }
+        self.app_control.device_checked = Some(Instant::now());
+
+        if self.audio.is_offline() {
+            let wanted = self.setting.output_device.to_string();
+            self.audio.set_output_device(&wanted);
+            if !self.audio.is_offline() {
+                util::log("audio device available");
+                self.reset_next_song();
+                self.app_control.output_devices = audio::output_devices();
+            }
+            return;
+        }
+
+        let devices = audio::output_devices();
+        let wanted = &self.setting.output_device;
+        let available = devices.contains(wanted);
+        let using = self.audio.device() == Some(wanted);
+        if available != using {
+            util::log(format!("output device {} available={}", wanted, available));
+            let wanted = wanted.to_string();
+            self.audio.set_output_device(&wanted);
+            self.reset_next_song();
+        }
+        self.app_control.output_devices = devices;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::SimEngine;
+
+    const TRACK_SECS: f32 = 10.;
+    // 两个文件夹，每个文件夹两张专辑，每张专辑两首
+    const TRACKS: [(&str, &str); 8] = [
+        ("/music/a/1.mp3", "A"),
+        ("/music/a/2.mp3", "A"),
+        ("/music/a/3.mp3", "B"),
+        ("/music/a/4.mp3", "B"),
+        ("/music/b/5.mp3", "C"),
+        ("/music/b/6.mp3", "C"),
+        ("/music/b/7.mp3", "D"),
+        ("/music/b/8.mp3", "D"),
+    ];
+
+    // 不在播放列表中的歌曲，用于待播队列
+    const OTHERS: [&str; 2] = ["/other/x.mp3", "/other/y.mp3"];
+
+    fn modes() -> [PlayMode; 4] {
+        [
+            PlayMode::Sequence,
+            PlayMode::Random,
+            PlayMode::ShuffleAlbum,
+            PlayMode::ShuffleFolder,
+        ]
+    }
+
+    fn music_info(index: usize) -> MusicInfo {
+        let (path, album) = TRACKS[index];
+        MusicInfo {
+            title: path.to_string(),
+            album: album.to_string(),
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn path(index: usize) -> String {
+        TRACKS[index].0.to_string()
+    }
+
+    fn other(index: usize) -> MusicInfo {
+        MusicInfo {
+            title: OTHERS[index].to_string(),
+            path: OTHERS[index].to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 播放列表为 TRACKS，从第一首开始播放，missing 中的歌曲无法打开
+    fn player(mode: PlayMode, repeat: Repeat, missing: &[usize]) -> SilkPlayer<SimEngine> {
+        let tracks: Vec<(&str, f32)> = (0..TRACKS.len())
+            .filter(|index| !missing.contains(index))
+            .map(|index| TRACKS[index].0)
+            .chain(OTHERS)
+            .map(|path| (path, TRACK_SECS))
+            .collect();
+        // 清掉上次运行留下的数据，每次都从空白状态开始
+        let _ = std::fs::remove_dir_all(util::data_dir());
+        let mut app = SilkPlayer {
+            audio: SimEngine::new(&tracks),
+            ..Default::default()
+        };
+        app.setting.play_mode = mode;
+        app.setting.repeat = repeat;
+        app.setting.crossfade = 0.;
+        app.setting.crossfade_manual = 0.;
+        *app.play_list.all_list.lock().unwrap() = (0..TRACKS.len()).map(music_info).collect();
+        app.play_list.filter();
+        let _ = app.change_play_list(SongControl::List(music_info(0)));
+        app
+    }
+
+    /// 每次前进 1 秒并处理音频事件，直到打开了 count 首或播放停止
+    fn play_until(app: &mut SilkPlayer<SimEngine>, count: usize) {
+        for _ in 0..1000 {
+            let stopped = !app.audio.is_play() && !app.audio.has_events();
+            if app.audio.loaded.len() >= count || stopped {
+                return;
+            }
+            app.audio.advance(1.);
+            app.poll_audio();
+        }
+        panic!("playback did not reach {} tracks", count);
+    }
+
+    /// 完整播放一轮：每首恰好一次，并符合播放模式的顺序
+    fn assert_one_pass(mode: &PlayMode, played: &[String]) {
+        let mut sorted = played.to_vec();
+        sorted.sort();
+        let all: Vec<String> = (0..TRACKS.len()).map(path).collect();
+        assert_eq!(sorted, all, "{} played {:?}", mode.name(), played);
+        assert_eq!(played[0], path(0), "{}", mode.name());
+
+        // 同组歌曲按列表顺序连续播放
+        let group_size = match mode {
+            PlayMode::Sequence => TRACKS.len(),
+            PlayMode::Random => 1,
+            PlayMode::ShuffleAlbum => 2,
+            PlayMode::ShuffleFolder => 4,
+        };
+        for group in played.chunks(group_size) {
+            let start = all.iter().position(|item| *item == group[0]).unwrap();
+            assert_eq!(start % group_size, 0, "{} played {:?}", mode.name(), played);
+            assert_eq!(
+                group,
+                &all[start..start + group_size],
+                "{} played {:?}",
+                mode.name(),
+                played
+            );
+        }
+    }
+
+    #[test]
+    fn repeat_off_plays_each_track_once_then_stops() {
+        for mode in modes() {
+            let mut app = player(mode, Repeat::Off, &[]);
+            play_until(&mut app, usize::MAX);
+            let mode = &app.setting.play_mode;
+            let loaded = &app.audio.loaded;
+            assert_one_pass(mode, &loaded[..TRACKS.len()]);
+            // 播完最后一首后重新打开并停在开头
+            assert_eq!(loaded.len(), TRACKS.len() + 1, "{}", mode.name());
+            assert_eq!(loaded[TRACKS.len()], loaded[TRACKS.len() - 1]);
+            assert!(!app.audio.is_play());
+            assert_eq!(app.audio.position(), 0.);
+            assert_eq!(app.app_control.history_list, loaded[..TRACKS.len()]);
+        }
+    }
+
+    #[test]
+    fn repeat_all_keeps_playing_after_the_last_track() {
+        for mode in modes() {
+            let mut app = player(mode, Repeat::All, &[]);
+            play_until(&mut app, TRACKS.len() * 2 + 1);
+            let mode = &app.setting.play_mode;
+            let loaded = &app.audio.loaded;
+            assert_one_pass(mode, &loaded[..TRACKS.len()]);
+            assert!(app.audio.is_play(), "{}", mode.name());
+            assert!(
+                loaded.windows(2).all(|pair| pair[0] != pair[1]),
+                "{} played {:?}",
+                mode.name(),
+                loaded
+            );
+            if *mode == PlayMode::Sequence {
+                assert_eq!(loaded[TRACKS.len()], path(0));
+            }
+        }
+    }
+
+    #[test]
+    fn repeat_one_replays_until_skipped() {
+        for mode in modes() {
+            let mut app = player(mode, Repeat::One, &[]);
+            play_until(&mut app, 3);
+            assert_eq!(
+                app.audio.loaded,
+                vec![path(0); 3],
+                "{}",
+                app.setting.play_mode.name()
+            );
+            assert_eq!(app.app_control.history_list, vec![path(0); 3]);
+
+            // 手动切歌时不受单曲循环影响
+            let _ = app.change_play_list(SongControl::PlayNext(true));
+            let next = app.audio.path().unwrap().to_string();
+            assert_ne!(next, path(0), "{}", app.setting.play_mode.name());
+            if app.setting.play_mode != PlayMode::Random {
+                assert_eq!(next, path(1), "{}", app.setting.play_mode.name());
+            }
+            play_until(&mut app, 5);
+            assert_eq!(app.audio.loaded[3..], [next.to_string(), next]);
+        }
+    }
+
+    #[test]
+    fn auto_advance_with_and_without_preload() {
+        let mut app = player(PlayMode::Sequence, Repeat::All, &[]);
+        // 临近结尾时预加载下一首，到点后无缝衔接
+        app.audio.advance(TRACK_SECS - PRELOAD_SECS);
+        app.poll_audio();
+        assert_eq!(app.audio.next_path(), Some(path(1).as_str()));
+        assert_eq!(app.app_control.next_song, Some(music_info(1)));
+        app.audio.advance(PRELOAD_SECS);
+        app.poll_audio();
+        assert_eq!(app.current_song.path, path(1));
+        assert_eq!(app.app_control.next_song, None);
+
+        // 没有预加载时由结束事件切到下一首
+        app.audio.advance(TRACK_SECS);
+        app.poll_audio();
+        assert_eq!(app.current_song.path, path(2));
+        assert_eq!(app.audio.loaded, [path(0), path(1), path(2)]);
+        assert_eq!(app.app_control.history_list, app.audio.loaded);
+    }
+
+    #[test]
+    fn previous_song_follows_play_order() {
+        for mode in modes() {
+            let mut app = player(mode, Repeat::All, &[]);
+            play_until(&mut app, 3);
+            let played = app.audio.loaded.clone();
+            let _ = app.change_play_list(SongControl::PlayNext(false));
+            assert_eq!(
+                app.current_song.path,
+                played[1],
+                "{}",
+                app.setting.play_mode.name()
+            );
+            let _ = app.change_play_list(SongControl::PlayNext(false));
+            assert_eq!(
+                app.current_song.path,
+                played[0],
+                "{}",
+                app.setting.play_mode.name()
+            );
+            // 前进时沿原来的顺序
+            let _ = app.change_play_list(SongControl::PlayNext(true));
+            assert_eq!(
+                app.current_song.path,
+                played[1],
+                "{}",
+                app.setting.play_mode.name()
+            );
+            assert_eq!(
+                app.app_control.history_list[3..],
+                [
+                    played[1].to_string(),
+                    played[0].to_string(),
+                    played[1].to_string()
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn queue_edits_change_what_plays_next() {
+        let mut app = player(PlayMode::Sequence, Repeat::All, &[]);
+        let queued = |app: &SilkPlayer<SimEngine>| -> Vec<String> {
+            let up_next = app.app_control.up_next.iter();
+            up_next.map(|item| item.path.to_string()).collect()
+        };
+        for item in [other(0), music_info(4), other(0)] {
+            let _ = app.change_play_list(SongControl::Queue(item));
+        }
+        let _ = app.change_play_list(SongControl::Next(other(1)));
+        assert_eq!(queued(&app), [OTHERS[1], OTHERS[0], TRACKS[4].0]);
+
+        let _ = app.change_play_list(SongControl::Dequeue(2));
+        assert_eq!(queued(&app), [OTHERS[1], OTHERS[0]]);
+
+        // 播完待播队列后从之前的列表位置继续
+        play_until(&mut app, 4);
+        assert_eq!(
+            app.audio.loaded,
+            [
+                path(0),
+                OTHERS[1].to_string(),
+                OTHERS[0].to_string(),
+                path(1)
+            ]
+        );
+        assert!(app.app_control.up_next.is_empty());
+    }
+
+    #[test]
+    fn queue_edits_replace_the_preloaded_track() {
+        let mut app = player(PlayMode::Sequence, Repeat::All, &[]);
+        app.audio.advance(TRACK_SECS - PRELOAD_SECS);
+        app.poll_audio();
+        assert_eq!(app.audio.next_path(), Some(path(1).as_str()));
+
+        let _ = app.change_play_list(SongControl::Queue(other(0)));
+        assert_eq!(app.audio.next_path(), None);
+        play_until(&mut app, 2);
+        assert_eq!(app.audio.loaded, [path(0), OTHERS[0].to_string()]);
+
+        let _ = app.change_play_list(SongControl::Queue(other(1)));
+        let _ = app.change_play_list(SongControl::ClearQueue);
+        play_until(&mut app, 3);
+        assert_eq!(app.audio.loaded[2], path(1));
+    }
+
+    #[test]
+    fn unplayable_tracks_are_skipped() {
+        let mut app = player(PlayMode::Sequence, Repeat::Off, &[1, 2]);
+        play_until(&mut app, 2);
+        assert_eq!(app.audio.loaded, [path(0), path(3)]);
+        assert_eq!(app.app_control.history_list, app.audio.loaded);
+        assert_eq!(app.current_song.path, path(3));
     }
 }
@@ -0,0 +1,252 @@
+use mp4ameta::FreeformIdent;
+use serde::{Deserialize, Serialize};
+
+use crate::{loudness, util};
+
+const ITUNES_MEAN: &str = "com.apple.iTunes";
+
+/// 响度均衡模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    Album,
+}
+impl ReplayGainMode {
+    pub const ALL: [Self; 3] = [
+        ReplayGainMode::Off,
+        ReplayGainMode::Track,
+        ReplayGainMode::Album,
+    ];
+}
+impl std::fmt::Display for ReplayGainMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ReplayGainMode::Off => "关闭",
+                ReplayGainMode::Track => "单曲",
+                ReplayGainMode::Album => "专辑",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct ReplayGainConfig {
+    pub mode: ReplayGainMode,
+    pub preamp: f32,        // 前置增益，单位：dB
+    pub prevent_clip: bool, // 根据峰值防止削波
+}
+impl Default for ReplayGainConfig {
+    fn default() -> Self {
+        Self {
+            mode: Default::default(),
+            preamp: 0.0,
+            prevent_clip: true,
+        }
+    }
+}
+
+/// 歌曲标签中的 ReplayGain 信息，增益单位：dB，峰值为线性幅度
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+impl ReplayGain {
+    /// 读取 ReplayGain 信息，没有标签时使用响度分析的结果
+    pub fn read(path: &str) -> ReplayGain {
+        let mut replay_gain = ReplayGain::read_tags(path);
+        if replay_gain.track_gain.is_none() {
+            if let Some(loudness) = loudness::cached(path) {
+                replay_gain.track_gain = Some(loudness.gain());
+                replay_gain.track_peak = replay_gain.track_peak.or(Some(loudness.peak));
+            }
+        }
+        replay_gain
+    }
+
+    /// 读取 ReplayGain / R128 标签
+    pub fn read_tags(path: &str) -> ReplayGain {
+        let ext = std::path::Path::new(path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let mut replay_gain = ReplayGain::default();
+        match ext.as_str() {
+            "mp3" => match id3::Tag::read_from_path(path) {
+                Ok(tag) => {
+                    for text in tag.extended_texts() {
+                        replay_gain.set(&text.description, &text.value);
+                    }
+                }
+                Err(err) => util::log_err(format!("read id3 err path={} err={}", path, err)),
+            },
+            "flac" => match metaflac::Tag::read_from_path(path) {
+                Ok(tag) => {
+                    if let Some(comments) = tag.vorbis_comments() {
+                        for (key, values) in comments.comments.iter() {
+                            if let Some(value) = values.first() {
+                                replay_gain.set(key, value);
+                            }
+                        }
+                    }
+                }
+                Err(err) => util::log_err(format!("read flac err path={} err={}", path, err)),
+            },
+            "m4a" | "m4b" => match mp4ameta::Tag::read_from_path(path) {
+                Ok(tag) => {
+                    for key in [
+                        "replaygain_track_gain",
+                        "replaygain_track_peak",
+                        "replaygain_album_gain",
+                        "replaygain_album_peak",
+                    ] {
+                        let upper = key.to_uppercase();
+                        for name in [key, upper.as_str()] {
+                            let ident = FreeformIdent::new(ITUNES_MEAN, name);
+                            let value = tag.strings_of(&ident).next().map(|v| v.to_string());
+                            if let Some(value) = value {
+                                replay_gain.set(name, &value);
+                            }
+                        }
+                    }
+                }
+                Err(err) => util::log_err(format!("read m4a err path={} err={}", path, err)),
+            },
+            _ => {}
+        }
+        replay_gain
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        match key.to_uppercase().as_str() {
+            "REPLAYGAIN_TRACK_GAIN" => self.track_gain = parse_db(value),
+            "REPLAYGAIN_TRACK_PEAK" => self.track_peak = parse_db(value),
+            "REPLAYGAIN_ALBUM_GAIN" => self.album_gain = parse_db(value),
+            "REPLAYGAIN_ALBUM_PEAK" => self.album_peak = parse_db(value),
+            // R128 为 Q7.8 定点数，参考响度 -23 LUFS，换算到 ReplayGain 的 -18 LUFS
+            "R128_TRACK_GAIN" if self.track_gain.is_none() => {
+                self.track_gain = parse_r128(value);
+            }
+            "R128_ALBUM_GAIN" if self.album_gain.is_none() => {
+                self.album_gain = parse_r128(value);
+            }
+            _ => {}
+        }
+    }
+
+    /// 按设置计算需要乘到音量上的线性增益
+    pub fn factor(&self, config: &ReplayGainConfig) -> f32 {
+        let (gain, peak) = match config.mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track => (
+                self.track_gain.or(self.album_gain),
+                self.track_peak.or(self.album_peak),
+            ),
+            ReplayGainMode::Album => (
+                self.album_gain.or(self.track_gain),
+                self.album_peak.or(self.track_peak),
+            ),
+        };
+        let gain = match gain {
+            Some(gain) => gain,
+            None => return 1.0,
+        };
+
+        let mut factor = db_to_amplitude(gain + config.preamp);
+        if config.prevent_clip {
+            if let Some(peak) = peak {
+                if peak > 0. && factor * peak > 1.0 {
+                    factor = 1.0 / peak;
+                }
+            }
+        }
+        factor
+    }
+}
+
+/// 将分析得到的单曲增益和峰值写入歌曲标签
+pub fn write_track_gain(path: &str, gain: f32, peak: f32) -> Result<(), String> {
+    let gain = format!("{:.2} dB", gain);
+    let peak = format!("{:.6}", peak);
+    let ext = std::path::Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "mp3" => {
+            use id3::TagLike;
+            let mut tag = match id3::Tag::read_from_path(path) {
+                Ok(tag) => tag,
+                Err(id3::Error {
+                    kind: id3::ErrorKind::NoTag,
+                    ..
+                }) => id3::Tag::new(),
+                Err(err) => return Err(err.to_string()),
+            };
+            let version = tag.version();
+            for (description, value) in [
+                ("REPLAYGAIN_TRACK_GAIN", gain),
+                ("REPLAYGAIN_TRACK_PEAK", peak),
+            ] {
+                tag.remove_extended_text(Some(description), None);
+                tag.add_frame(id3::frame::ExtendedText {
+                    description: description.to_string(),
+                    value,
+                });
+            }
+            tag.write_to_path(path, version)
+                .map_err(|err| err.to_string())
+        }
+        "flac" => {
+            let mut tag = metaflac::Tag::read_from_path(path).map_err(|err| err.to_string())?;
+            tag.set_vorbis("REPLAYGAIN_TRACK_GAIN", vec![gain]);
+            tag.set_vorbis("REPLAYGAIN_TRACK_PEAK", vec![peak]);
+            tag.save().map_err(|err| err.to_string())
+        }
+        "m4a" | "m4b" => {
+            let mut tag = mp4ameta::Tag::read_from_path(path).map_err(|err| err.to_string())?;
+            for (name, value) in [
+                ("replaygain_track_gain", gain),
+                ("replaygain_track_peak", peak),
+            ] {
+                tag.remove_data_of(&FreeformIdent::new(ITUNES_MEAN, &name.to_uppercase()));
+                tag.set_data(
+                    FreeformIdent::new(ITUNES_MEAN, name),
+                    mp4ameta::Data::Utf8(value),
+                );
+            }
+            tag.write_to_path(path).map_err(|err| err.to_string())
+        }
+        _ => Err(format!("unsupported file type {}", ext)),
+    }
+}
+
+pub fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.)
+}
+
+/// 解析 "-7.03 dB" 或 "0.988831" 这样的数值
+fn parse_db(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .or_else(|| value.strip_suffix("DB"))
+        .unwrap_or(value);
+    value.trim().parse::<f32>().ok()
+}
+
+fn parse_r128(value: &str) -> Option<f32> {
+    value
+        .trim()
+        .parse::<i32>()
+        .ok()
+        .map(|q78| q78 as f32 / 256. + 5.)
+}
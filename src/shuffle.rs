@@ -0,0 +1,247 @@
+use std::collections::{HashMap, HashSet};
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+/// 随机播放顺序，对播放列表整体洗牌，pos 之前为已播放部分，上一首沿顺序回退
+///
+/// 歌曲按分组洗牌：同组的歌曲保持列表中的先后顺序连续播放，只打乱分组之间的顺序。
+/// 分组为歌曲自身路径时即逐首随机
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShuffleOrder {
+    order: Vec<String>,
+    pos: usize, // 当前歌曲在 order 中的位置
+}
+
+/// 参与洗牌的歌曲，(路径, 分组)
+pub type ShuffleTrack = (String, String);
+
+impl ShuffleOrder {
+    pub fn clear(&mut self) {
+        self.order.clear();
+        self.pos = 0;
+    }
+
+    /// 以当前歌曲所在分组开头重新洗牌
+    pub fn rebuild(&mut self, tracks: &[ShuffleTrack], current: &str) {
+        let mut groups: Vec<(&String, Vec<String>)> = vec![];
+        let mut group_index: HashMap<&String, usize> = HashMap::new();
+        for (path, group) in tracks {
+            let index = *group_index.entry(group).or_insert_with(|| {
+                groups.push((group, vec![]));
+                groups.len() - 1
+            });
+            groups[index].1.push(path.to_string());
+        }
+        let first = groups
+            .iter()
+            .position(|(_, paths)| paths.iter().any(|path| path == current))
+            .map(|index| groups.remove(index));
+        groups.shuffle(&mut rand::thread_rng());
+
+        self.order.clear();
+        self.pos = 0;
+        if let Some((_, paths)) = first {
+            self.pos = paths.iter().position(|path| path == current).unwrap_or(0);
+            self.order.extend(paths);
+        }
+        for (_, paths) in groups {
+            self.order.extend(paths);
+        }
+    }
+
+    /// 与播放列表同步：移除已不在列表中的歌曲，新增的歌曲插入剩余顺序的随机位置，
+    /// 同组已有歌曲还未播放时紧跟在同组之后
+    pub fn sync(&mut self, tracks: &[ShuffleTrack], current: &str) {
+        if self.order.get(self.pos).is_none_or(|path| path != current) {
+            match self.order.iter().position(|path| path == current) {
+                Some(index) => self.pos = index,
+                None => return self.rebuild(tracks, current),
+            }
+        }
+
+        let groups: HashMap<&String, &String> =
+            tracks.iter().map(|(path, group)| (path, group)).collect();
+        let mut index = 0;
+        let pos = self.pos;
+        self.order.retain(|path| {
+            let keep = groups.contains_key(path) || index == pos;
+            if !keep && index < pos {
+                self.pos -= 1;
+            }
+            index += 1;
+            keep
+        });
+
+        let group_of = |path: &String| groups.get(path).copied();
+        // 当前位置之后各分组的最后一首，以及分组之间的位置（只插在这里，不拆散其他分组），每次同步只计算一次
+        let mut last_of_group: HashMap<&String, usize> = HashMap::new();
+        for index in self.pos..self.order.len() {
+            if let Some(group) = group_of(&self.order[index]) {
+                last_of_group.insert(group, index);
+            }
+        }
+        let bounds: Vec<usize> = (self.pos + 1..=self.order.len())
+            .filter(|index| {
+                *index == self.order.len()
+                    || group_of(&self.order[index - 1]) != group_of(&self.order[*index])
+            })
+            .collect();
+
+        // slots[i] 为插在原顺序第 i 首之前的歌曲：先是紧跟同组的，再是新分组，同一新分组连续
+        let known: HashSet<&String> = self.order.iter().collect();
+        let mut slots: Vec<Vec<String>> = vec![vec![]; self.order.len() + 1];
+        let mut new_groups: Vec<(usize, Vec<String>)> = vec![];
+        let mut new_group_index: HashMap<&String, usize> = HashMap::new();
+        let mut rng = rand::thread_rng();
+        for (path, group) in tracks {
+            if known.contains(path) {
+                continue;
+            }
+            if let Some(index) = last_of_group.get(group) {
+                slots[index + 1].push(path.to_string());
+                continue;
+            }
+            let index = *new_group_index.entry(group).or_insert_with(|| {
+                new_groups.push((bounds[rng.gen_range(0..bounds.len())], vec![]));
+                new_groups.len() - 1
+            });
+            new_groups[index].1.push(path.to_string());
+        }
+        if slots.iter().all(Vec::is_empty) && new_groups.is_empty() {
+            return;
+        }
+        for (index, paths) in new_groups {
+            slots[index].extend(paths);
+        }
+
+        let order = std::mem::take(&mut self.order);
+        for (path, slot) in order.into_iter().zip(slots.iter_mut()) {
+            self.order.append(slot);
+            self.order.push(path);
+        }
+        if let Some(slot) = slots.last_mut() {
+            self.order.append(slot);
+        }
+    }
+
+    /// 下一首，不改变当前位置；本轮播完时 wrap 为 true 则重新洗牌开始新一轮，否则返回 None
+    pub fn peek_next(&mut self, tracks: &[ShuffleTrack], current: &str, wrap: bool) -> Option<String> {
+        if current.is_empty() {
+            self.rebuild(tracks, current);
+            return self.order.first().cloned();
+        }
+        self.sync(tracks, current);
+        if self.pos + 1 >= self.order.len() {
+            if !wrap {
+                return None;
+            }
+            self.rebuild(tracks, current);
+            // 列表只有当前分组时从头开始
+            return self.order.get(self.pos + 1).or(self.order.first()).cloned();
+        }
+        self.order.get(self.pos + 1).cloned()
+    }
+
+    /// 上一首，已回到本轮开头时返回 None
+    pub fn peek_prev(&mut self, tracks: &[ShuffleTrack], current: &str) -> Option<String> {
+        self.sync(tracks, current);
+        if self.pos == 0 {
+            return None;
+        }
+        self.order.get(self.pos - 1).cloned()
+    }
+
+    /// 歌曲正是当前位置的前一首或后一首
+    pub fn is_adjacent(&self, path: &str) -> bool {
+        let at = |index: usize| self.order.get(index).is_some_and(|item| item == path);
+        at(self.pos + 1) || (self.pos > 0 && at(self.pos - 1))
+    }
+
+    /// 歌曲开始播放，前进或后退一步；直接选择的歌曲移到当前位置之后再前进
+    pub fn played(&mut self, path: &str) {
+        if self.order.is_empty() || self.order[self.pos] == path {
+            return;
+        }
+        if self.order.get(self.pos + 1).is_some_and(|item| item == path) {
+            self.pos += 1;
+        } else if self.pos > 0 && self.order[self.pos - 1] == path {
+            self.pos -= 1;
+        } else {
+            if let Some(index) = self.order.iter().position(|item| item == path) {
+                self.order.remove(index);
+                if index < self.pos {
+                    self.pos -= 1;
+                }
+            }
+            self.pos = (self.pos + 1).min(self.order.len());
+            self.order.insert(self.pos, path.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracks(items: &[(&str, &str)]) -> Vec<ShuffleTrack> {
+        items
+            .iter()
+            .map(|(path, group)| (path.to_string(), group.to_string()))
+            .collect()
+    }
+
+    /// 顺序中每个分组都是连续的一段
+    fn assert_grouped(order: &[String], tracks: &[ShuffleTrack]) {
+        let group_of = |path: &String| &tracks.iter().find(|(item, _)| item == path).unwrap().1;
+        let mut seen = HashSet::new();
+        for (index, path) in order.iter().enumerate() {
+            let group = group_of(path);
+            if index > 0 && group_of(&order[index - 1]) == group {
+                continue;
+            }
+            assert!(seen.insert(group), "group {} split in {:?}", group, order);
+        }
+    }
+
+    #[test]
+    fn rebuild_starts_with_the_current_group() {
+        let tracks = tracks(&[
+            ("a1", "a"),
+            ("a2", "a"),
+            ("b1", "b"),
+            ("c1", "c"),
+            ("c2", "c"),
+        ]);
+        let mut shuffle = ShuffleOrder::default();
+        shuffle.rebuild(&tracks, "c2");
+        assert_eq!(shuffle.order[..2], ["c1", "c2"]);
+        assert_eq!(shuffle.pos, 1);
+        assert_eq!(shuffle.order.len(), tracks.len());
+        assert_grouped(&shuffle.order, &tracks);
+    }
+
+    #[test]
+    fn sync_keeps_groups_together() {
+        let mut tracks = tracks(&[("a1", "a"), ("a2", "a"), ("b1", "b"), ("b2", "b")]);
+        let mut shuffle = ShuffleOrder::default();
+        shuffle.rebuild(&tracks, "a1");
+
+        // 已有分组的新歌曲紧跟在同组之后，新分组不拆散其他分组
+        tracks.retain(|(path, _)| path != "b2");
+        tracks.extend(self::tracks(&[
+            ("b3", "b"),
+            ("c1", "c"),
+            ("a3", "a"),
+            ("c2", "c"),
+        ]));
+        shuffle.sync(&tracks, "a1");
+        assert_eq!(shuffle.order[..3], ["a1", "a2", "a3"]);
+        assert_eq!(shuffle.pos, 0);
+        assert_eq!(shuffle.order.len(), tracks.len());
+        assert!(!shuffle.order.contains(&"b2".to_string()));
+        let b1 = shuffle.order.iter().position(|path| path == "b1").unwrap();
+        assert_eq!(shuffle.order[b1 + 1], "b3");
+        assert_grouped(&shuffle.order, &tracks);
+    }
+}
@@ -0,0 +1,219 @@
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveTime};
+use iced::{
+    widget::{button, row, text},
+    Alignment,
+};
+
+use crate::{engine::PlayEngine, util, Message, SilkPlayer, View};
+
+const CYCLE_MINUTES: [u32; 4] = [15, 30, 60, 90]; // 快捷键依次切换的定时时长
+
+/// 定时关闭方式
+#[derive(Debug, Clone, PartialEq)]
+pub enum SleepMode {
+    Minutes(u32),     // N 分钟后
+    At(String),       // 到达 HH:MM 时
+    AfterCurrent,     // 播完当前歌曲
+    AfterTracks(u32), // 播完 N 首歌曲，包含当前歌曲
+}
+
+#[derive(Debug, Clone)]
+pub enum SleepControl {
+    Start(SleepMode),
+    Cycle, // 依次切换预设时长，最后取消
+    Cancel,
+    Minutes(String),
+    At(String),
+    Tracks(String),
+}
+
+#[derive(Default)]
+pub struct SleepTimer {
+    mode: Option<SleepMode>,
+    deadline: Option<Instant>, // 按时间定时的结束时刻
+    tracks_left: u32,          // 按歌曲数定时剩余的歌曲数
+    fading: bool,
+    pub minutes_input: String,
+    pub at_input: String,
+    pub tracks_input: String,
+}
+
+impl SleepTimer {
+    pub fn mode(&self) -> Option<&SleepMode> {
+        self.mode.as_ref()
+    }
+    /// 当前歌曲播完后停止
+    pub fn stop_at_track_end(&self) -> bool {
+        self.deadline.is_none() && self.mode.is_some() && self.tracks_left <= 1
+    }
+    /// 剩余的歌曲数，仅按歌曲数定时时有效
+    pub fn tracks_left(&self) -> Option<u32> {
+        match self.mode {
+            Some(SleepMode::AfterCurrent) | Some(SleepMode::AfterTracks(_)) => {
+                Some(self.tracks_left)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<E: PlayEngine> SilkPlayer<E> {
+    pub fn sleep_control(&mut self, control: SleepControl) {
+        match control {
+            SleepControl::Start(mode) => self.start_sleep_timer(mode),
+            SleepControl::Cycle => {
+                let next = match self.sleep_timer.mode {
+                    None => Some(CYCLE_MINUTES[0]),
+                    Some(SleepMode::Minutes(minutes)) => CYCLE_MINUTES
+                        .iter()
+                        .find(|item| **item > minutes)
+                        .copied(),
+                    Some(_) => None,
+                };
+                match next {
+                    Some(minutes) => self.start_sleep_timer(SleepMode::Minutes(minutes)),
+                    None => self.cancel_sleep_timer(),
+                }
+            }
+            SleepControl::Cancel => self.cancel_sleep_timer(),
+            SleepControl::Minutes(value) => self.sleep_timer.minutes_input = value,
+            SleepControl::At(value) => self.sleep_timer.at_input = value,
+            SleepControl::Tracks(value) => self.sleep_timer.tracks_input = value,
+        }
+    }
+
+    pub fn start_sleep_timer(&mut self, mode: SleepMode) {
+        self.cancel_sleep_timer();
+        let timer = &mut self.sleep_timer;
+        match mode {
+            SleepMode::Minutes(minutes) => {
+                if minutes == 0 {
+                    return;
+                }
+                timer.deadline = Some(Instant::now() + Duration::from_secs(minutes as u64 * 60));
+            }
+            SleepMode::At(ref at) => {
+                let target = match NaiveTime::parse_from_str(at.trim(), "%H:%M") {
+                    Ok(target) => target,
+                    Err(err) => {
+                        util::log_err(format!("parse sleep time {} err={}", at, err));
+                        return;
+                    }
+                };
+                let now = Local::now().time();
+                let mut secs = (target - now).num_seconds();
+                if secs <= 0 {
+                    secs += 24 * 3600; // 已过的时刻视为明天
+                }
+                timer.deadline = Some(Instant::now() + Duration::from_secs(secs as u64));
+            }
+            SleepMode::AfterCurrent => timer.tracks_left = 1,
+            SleepMode::AfterTracks(tracks) => {
+                if tracks == 0 {
+                    return;
+                }
+                timer.tracks_left = tracks;
+            }
+        }
+        util::log(format!("sleep timer start {:?}", mode));
+        timer.mode = Some(mode);
+        // 需要在当前歌曲结束时停止，已预加载的下一首作废
+        if timer.stop_at_track_end() {
+            self.reset_next_song();
+        }
+    }
+
+    pub fn cancel_sleep_timer(&mut self) {
+        if self.sleep_timer.fading {
+            self.audio.restore_volume();
+        }
+        let timer = &mut self.sleep_timer;
+        timer.mode = None;
+        timer.deadline = None;
+        timer.tracks_left = 0;
+        timer.fading = false;
+    }
+
+    /// 距离停止播放的剩余时间，单位：s
+    pub fn sleep_remaining(&self) -> Option<f32> {
+        let timer = &self.sleep_timer;
+        if let Some(deadline) = timer.deadline {
+            return Some(
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_secs_f32(),
+            );
+        }
+        if timer.stop_at_track_end() {
+            let remain = (self.audio.duration() - self.audio.position()).max(0.);
+            return Some(remain / self.audio.speed());
+        }
+        None
+    }
+
+    /// 定时刷新时检查，临近结束时淡出，到点后暂停
+    pub fn check_sleep_timer(&mut self) {
+        let remaining = match self.sleep_remaining() {
+            Some(remaining) => remaining,
+            None => return,
+        };
+        let track_end = self.sleep_timer.deadline.is_none() && self.audio.is_over();
+        if remaining <= 0. || track_end {
+            // 暂停期间到点的直接取消
+            if self.audio.is_play() {
+                util::log("sleep timer done, pause");
+                self.audio.pause();
+                self.save_play_status();
+            }
+            self.cancel_sleep_timer();
+        } else if self.audio.is_play()
+            && !self.sleep_timer.fading
+            && remaining <= self.setting.sleep_fade
+        {
+            self.audio.fade_out(remaining);
+            self.sleep_timer.fading = true;
+        }
+    }
+
+    /// 倒计时描述，未开启时返回 None
+    pub fn sleep_label(&self) -> Option<String> {
+        let mode = self.sleep_timer.mode()?;
+        let countdown = self.sleep_remaining().map(util::play_time);
+        Some(match (mode, countdown) {
+            (SleepMode::At(at), Some(countdown)) => format!("{} 停止，剩余 {}", at, countdown),
+            (_, Some(countdown)) => format!("剩余 {}", countdown),
+            (_, None) => format!("剩余 {} 首", self.sleep_timer.tracks_left().unwrap_or(0)),
+        })
+    }
+
+    pub fn sleep_timer_view(&self) -> View<'_> {
+        match self.sleep_label() {
+            Some(label) => row!(
+                text(label),
+                button("取消").on_press(Message::SleepTimer(SleepControl::Cancel)),
+            )
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .into(),
+            None => text("未开启").into(),
+        }
+    }
+
+    /// 歌曲自然播完时扣减按歌曲数定时的剩余数量，手动切歌和跳转不计
+    pub fn sleep_track_finished(&mut self) {
+        let timer = &mut self.sleep_timer;
+        if timer.tracks_left().is_some() && timer.tracks_left > 1 {
+            timer.tracks_left -= 1;
+        }
+    }
+
+    /// 淡出途中切歌，新歌曲以正常音量开始，按剩余时间重新淡出
+    pub fn sleep_song_started(&mut self) {
+        if self.sleep_timer.fading {
+            self.sleep_timer.fading = false;
+            self.check_sleep_timer();
+        }
+    }
+}
@@ -261,6 +261,7 @@ pub enum ContainerStyle {
     Gradient { time: f32, colors: Vec<Color> },
     ExtraColor(Color),
     BackgroundWithAlpha(f32),
+    Primary(f32), // 主题色，参数为透明度
 }
 impl container::StyleSheet for ContainerStyle {
     type Style = Theme;
@@ -276,6 +277,11 @@ impl container::StyleSheet for ContainerStyle {
                 color.a = *a;
                 self.background_color(color)
             }
+            ContainerStyle::Primary(a) => {
+                let mut color = style.extended_palette().primary.strong.color;
+                color.a = *a;
+                self.background_color(color)
+            }
         }
     }
 }
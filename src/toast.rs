@@ -0,0 +1,79 @@
+use std::time::{Duration, Instant};
+
+use iced::{
+    theme,
+    widget::{button, column, container, horizontal_space, row, text},
+    Alignment, Length,
+};
+
+use crate::{style, Message, SilkPlayer, View};
+
+const TOAST_SECS: Duration = Duration::from_secs(6); // 提示自动消失的时间
+const MAX_TOASTS: usize = 3; // 同时显示的最多条数，超出时移除最早的
+
+/// 不打断操作的提示，显示在界面顶部，到时自动消失
+struct Toast {
+    message: String,
+    created: Instant,
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, message: impl Into<String>) {
+        self.toasts.push(Toast {
+            message: message.into(),
+            created: Instant::now(),
+        });
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+    }
+
+    pub fn dismiss(&mut self, index: usize) {
+        if index < self.toasts.len() {
+            self.toasts.remove(index);
+        }
+    }
+
+    /// 定时刷新时移除已到时的提示
+    pub fn expire(&mut self) {
+        self.toasts
+            .retain(|toast| toast.created.elapsed() < TOAST_SECS);
+    }
+}
+
+impl SilkPlayer {
+    pub fn toast_view(&self) -> Option<View<'_>> {
+        if self.toasts.toasts.is_empty() {
+            return None;
+        }
+        let mut toasts = column!().spacing(2);
+        for (index, toast) in self.toasts.toasts.iter().enumerate() {
+            toasts = toasts.push(
+                container(
+                    row!(
+                        text(&toast.message).size(16),
+                        horizontal_space(),
+                        button(text("关闭").size(14))
+                            .on_press(Message::DismissToast(index))
+                            .style(theme::Button::Custom(Box::new(
+                                style::ButtonType::Text.default(),
+                            ))),
+                    )
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+                )
+                .width(Length::Fill)
+                .padding([2, 10])
+                .style(theme::Container::Custom(Box::new(
+                    style::ContainerStyle::Primary(0.6),
+                ))),
+            );
+        }
+        Some(toasts.into())
+    }
+}
@@ -35,6 +35,18 @@ pub fn current_dir() -> String {
 }
 
 pub fn data_dir() -> String {
+    // 测试时写到临时目录，不影响工作目录下的数据
+    // 每个测试在以测试名命名的线程中运行，按线程名分开目录，并行的测试互不读写对方的数据
+    if cfg!(test) {
+        let thread = std::thread::current();
+        let name: String = thread
+            .name()
+            .unwrap_or("main")
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        return format!("{}/silk_player_test_{}", std::env::temp_dir().display(), name);
+    }
     format!("{}/data", current_dir())
 }
 pub fn cache_dir() -> String {
@@ -162,6 +174,7 @@ pub fn get_files(dir: &str, file_list: &mut Vec<String>) -> io::Result<()> {
             if !file_name.ends_with(".mp3")
                 && !file_name.ends_with(".flac")
                 && !file_name.ends_with(".m4a")
+                && !file_name.ends_with(".m4b")
             // && !file_name.ends_with(".ogg")
             {
                 continue;
@@ -395,9 +408,18 @@ fn remove_special_char(mut value: String) -> String {
     value
 }
 
+/// 读取歌曲标签，music_tag 只按扩展名识别 .m4a，.m4b 有声书同为 MP4 容器，按 m4a 读取
+pub fn read_music_tag(path: &str) -> Result<MusicTag, music_tag::Error> {
+    if path.to_lowercase().ends_with(".m4b") {
+        let tag = mp4ameta::Tag::read_from_path(path)?;
+        return MusicTag::try_from((tag, Some(path::PathBuf::from(path))));
+    }
+    MusicTag::read_from_path(path)
+}
+
 /// 获取专辑封面图片路径
 pub fn get_album_path(path: &str) -> String {
-    if let Ok(tag) = music_tag::audio::MusicTag::read_from_path(&path) {
+    if let Ok(tag) = read_music_tag(path) {
         get_album_path_by_tag(&tag)
     } else {
         format!("{}/assets/default.png", current_dir())
@@ -5,20 +5,25 @@ use std::{
 
 use iced::{
     theme, widget::{
-        button, column, container, row, scrollable, text, text_input, Column, Image, MouseArea, Scrollable, Slider, Text
+        button, column, container, horizontal_space, pick_list, row, scrollable, text, text_input, Column, Image, MouseArea, Scrollable, Slider, Text
     }, Alignment, Command, Length
 };
 // use iced_aw::FloatingElement;
 
 use crate::{
-    components::{self, button_icon, tooltip_text},
+    components::{self, button_icon, button_text, tooltip_text},
     config::ConfigMessage,
+    engine::PlayEngine,
     style::{self, ButtonType},
-    util, Message, MusicInfo, ShowLyric, SilkPlayer, SongControl, Status, Tab, View,
+    sleep::SleepControl,
+    util,
+    visualizer::VisualStyle,
+    waveform, AbLoop, Message, MusicInfo, ShowLyric, SilkPlayer, SongControl, Status, Tab, View,
     LYRIC_SCROLLABLE_ID, PLAY_LIST_SCROLLABLE_ID,
 };
 
 const BOTTOM_STATUS_HEIGHT: f32 = 120.;
+const SPEED_OPTIONS: [f32; 7] = [0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
 
 #[derive(Debug, Default, PartialEq)]
 pub enum InitState {
@@ -260,6 +265,9 @@ pub enum DetailTab {
     #[default]
     Lyric,
     Info,
+    UpNext,   // 待播队列
+    Chapters, // 章节列表
+    Bookmarks, // 书签列表
 }
 
 impl SilkPlayer {
@@ -468,13 +476,41 @@ impl SilkPlayer {
 
         let left_time = Text::new(util::play_time(self.audio.position()));
         let right_time = Text::new(util::play_time(self.current_song.time));
-        let progess_slider = Slider::new(
-            0.0..=self.audio.duration(),
-            self.app_control.current_duration,
-            Message::UpdateSongTime,
-        )
-        .on_release(Message::UpdateSongTimeRelease)
-        .style(theme::Slider::Custom(Box::new(style::SliderStyle(false))));
+        // 波形生成之前、或无法解码的歌曲使用普通滑块
+        let progess_slider: View = match self.app_control.waveform {
+            Some((ref path, ref peaks)) if *path == self.current_song.path && !peaks.is_empty() => {
+                waveform::seek_bar(peaks, self.app_control.current_duration, self.audio.duration())
+            }
+            _ => Slider::new(
+                0.0..=self.audio.duration(),
+                self.app_control.current_duration,
+                Message::UpdateSongTime,
+            )
+            .on_release(Message::UpdateSongTimeRelease)
+            .style(theme::Slider::Custom(Box::new(style::SliderStyle(false))))
+            .into(),
+        };
+        let progess_slider = column!(progess_slider, self.progress_marks());
+
+        let (ab_label, ab_tip) = match (self.app_control.loop_a, self.audio.loop_region()) {
+            (_, Some(_)) => ("A-B", "取消 A-B 循环"),
+            (Some(_), None) => ("A-", "设置 B 点"),
+            (None, None) => ("A-B", "设置 A 点"),
+        };
+        let ab_loop = tooltip_text(
+            button_text(
+                ab_label,
+                14.,
+                Message::SongControl(SongControl::AbLoop(AbLoop::Toggle)),
+                if self.audio.loop_region().is_some() {
+                    ButtonType::Primary.with_radius(5.)
+                } else {
+                    ButtonType::Text.with_radius(5.)
+                },
+            ),
+            ab_tip,
+            iced::widget::tooltip::Position::Top,
+        );
 
         let volume_icon = style::icon("volume", 20.);
         let volume_slider = Slider::new(0.0..=1.0, self.audio.volume(), |value| {
@@ -512,16 +548,63 @@ impl SilkPlayer {
             style::ButtonType::Primary.cycle(),
         );
 
+        let play_mode_icon = style::icon(&self.setting.play_mode.icon(), 20.);
+        let play_mode_content: View = match self.setting.play_mode.badge() {
+            Some(badge) => row!(play_mode_icon, text(badge).size(12))
+                .spacing(3)
+                .align_items(Alignment::Center)
+                .into(),
+            None => play_mode_icon.into(),
+        };
         let play_mode = tooltip_text(
+            button(play_mode_content)
+                .on_press(Message::ChangeConfig(crate::config::ConfigMessage::ChangePlayMode))
+                .style(theme::Button::Custom(Box::new(ButtonType::Primary.default()))),
+            &self.setting.play_mode.name(),
+            iced::widget::tooltip::Position::Top,
+        );
+        let repeat = tooltip_text(
             button_icon(
-                &self.setting.play_mode.icon(),
+                &self.setting.repeat.icon(),
                 20.,
-                Message::ChangeConfig(crate::config::ConfigMessage::ChangePlayMode),
+                Message::ChangeConfig(crate::config::ConfigMessage::ChangeRepeat),
                 ButtonType::Primary.default(),
             ),
-            &self.setting.play_mode.name(),
+            &self.setting.repeat.name(),
             iced::widget::tooltip::Position::Top,
         );
+        let speed_options: Vec<String> = SPEED_OPTIONS
+            .iter()
+            .map(|speed| format!("{:.2}x", speed))
+            .collect();
+        let speed = tooltip_text(
+            pick_list(
+                speed_options,
+                Some(format!("{:.2}x", self.audio.speed())),
+                |value| {
+                    let speed = value.trim_end_matches('x').parse::<f32>().unwrap_or(1.0);
+                    Message::ChangeConfig(ConfigMessage::ChangeSpeed(speed))
+                },
+            )
+            .text_size(14),
+            "播放速度",
+            iced::widget::tooltip::Position::Top,
+        );
+        // 定时关闭倒计时，点击取消
+        let sleep: View = match self.sleep_label() {
+            Some(label) => tooltip_text(
+                button_text(
+                    &format!("定时 {}", label),
+                    14.,
+                    Message::SleepTimer(SleepControl::Cancel),
+                    ButtonType::Text.with_radius(5.),
+                ),
+                "取消定时关闭",
+                iced::widget::tooltip::Position::Top,
+            )
+            .into(),
+            None => column!().into(),
+        };
         let desktop_lyric = tooltip_text(
             button_icon(
                 "destop_lyric",
@@ -551,14 +634,14 @@ impl SilkPlayer {
                 let progess = row!(left_time, progess_slider, right_time)
                     .align_items(Alignment::Center)
                     .spacing(2);
-                let control = row!(pre, play, next, progess)
+                let control = row!(pre, play, next, progess, ab_loop)
                     .spacing(5)
                     .align_items(Alignment::Center);
                 let title = column!(Text::new(&self.current_song.title),);
                 let middle = column!(title, control).spacing(5);
 
                 let mut container = container(
-                    row!(album, middle, play_mode, desktop_lyric, volume_setting)
+                    row!(album, middle, play_mode, repeat, speed, sleep, desktop_lyric, volume_setting)
                         .width(Length::Fill)
                         .spacing(20)
                         .align_items(Alignment::Center),
@@ -598,6 +681,10 @@ impl SilkPlayer {
                     play,
                     next,
                     play_mode,
+                    repeat,
+                    speed,
+                    ab_loop,
+                    sleep,
                     desktop_lyric,
                     volume_setting
                 )
@@ -631,6 +718,52 @@ impl SilkPlayer {
         }
     }
 
+    /// 进度条下方的区间标记，显示 A-B 循环的范围、章节和书签的位置
+    fn progress_marks(&self) -> View<'_> {
+        let duration = self.audio.duration();
+        // (开始, 结束, 透明度)，A-B 循环区间、章节与书签刻度
+        let mut ranges = vec![];
+        match (self.audio.loop_region(), self.app_control.loop_a) {
+            (Some((a, b)), _) => ranges.push((a, b, 0.8)),
+            (None, Some(a)) => ranges.push((a, a + duration * 0.005, 0.8)),
+            _ => {}
+        }
+        for chapter in self.current_song.chapters.iter().filter(|chapter| chapter.start > 0.) {
+            ranges.push((chapter.start, chapter.start + duration * 0.003, 0.4));
+        }
+        for bookmark in self.bookmarks() {
+            ranges.push((bookmark.position, bookmark.position + duration * 0.003, 0.6));
+        }
+        ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut marks = row!().height(3).width(Length::Fill);
+        if duration <= 0. {
+            return marks.into();
+        }
+        let portion = |secs: f32| ((secs / duration).clamp(0., 1.) * 1000.).round() as u16;
+        let mut last = 0;
+        for (start, end, alpha) in ranges {
+            let start = portion(start).max(last);
+            let end = portion(end).max(start + 1);
+            if start > last {
+                marks = marks.push(horizontal_space().width(Length::FillPortion(start - last)));
+            }
+            marks = marks.push(
+                container(horizontal_space())
+                    .width(Length::FillPortion(end - start))
+                    .height(Length::Fill)
+                    .style(theme::Container::Custom(Box::new(
+                        style::ContainerStyle::Primary(alpha),
+                    ))),
+            );
+            last = end;
+        }
+        if last < 1000 {
+            marks = marks.push(horizontal_space().width(Length::FillPortion(1000 - last)));
+        }
+        marks.into()
+    }
+
     pub fn tab_container(&self) -> View {
         let tab_container = match self.tab {
             Tab::Home => self.home_view(),
@@ -728,18 +861,22 @@ impl SilkPlayer {
             .center_y()
             .into()
         } else {
-            let mut list = column!().spacing(15);
+            let mut list = column!().spacing(5);
             for music_info in &self.play_list.page_list {
-                list = list.push(self.pack_music_info_list(music_info, false));
+                let content = self.show_name(true, music_info);
+                list = list.push(self.list_item_view(content, music_info));
             }
 
-            container(
-                Scrollable::new(list)
-                    .width(Length::Fill)
-                    .id(PLAY_LIST_SCROLLABLE_ID.clone()),
-            )
-            .padding(style::padding_left(50.0))
-            .into()
+            let list = Scrollable::new(list)
+                .width(Length::Fill)
+                .id(PLAY_LIST_SCROLLABLE_ID.clone());
+            let content = match self.list_edit_view() {
+                Some(toolbar) => column!(toolbar, list).spacing(10).into(),
+                None => View::from(list),
+            };
+            container(content)
+                .padding(style::padding_left(50.0))
+                .into()
         }
     }
 
@@ -779,28 +916,14 @@ impl SilkPlayer {
         .into()
     }
 
-    pub fn get_list_by_tag(&self) -> Vec<MusicInfo> {
-        let mut list = vec![];
-        // let mut list = column!(detail).spacing(15);
-        if let Ok(all_list) = self.music_list.all_list.try_lock() {
-            for music_info in all_list.iter() {
-                let tag_names: Vec<_> = music_info
-                    .tags
-                    .iter()
-                    .filter(|item| item.name.eq(&self.tag.name))
-                    .collect();
-                if tag_names.is_empty() {
-                    continue;
-                }
-                list.push(music_info.clone());
-            }
-        }
-        list
-    }
-
     pub fn like_detail_view(&self) -> View {
         let list = self.get_list_by_tag();
-        let info = column!(text(&self.tag.name), text(&self.tag.path).size(16)).spacing(15);
+        let info = column!(
+            text(&self.tag.name),
+            text(&self.tag.path).size(16),
+            self.tag_eq_view(&self.tag.path),
+        )
+        .spacing(15);
         let detail: View = if !list.is_empty() {
             row!(self.pack_album(&list[0]), info).spacing(15).into()
         } else {
@@ -853,13 +976,55 @@ impl SilkPlayer {
             )
         };
 
+        let play_next_btn = tooltip_text(
+            button_text(
+                "插播",
+                icon_size,
+                Message::SongControl(SongControl::Next(music_info.clone())),
+                style::ButtonType::Info.default(),
+            ),
+            "下一首播放",
+            iced::widget::tooltip::Position::Top,
+        );
+        let queue_btn = tooltip_text(
+            button_text(
+                "待播",
+                icon_size,
+                Message::SongControl(SongControl::Queue(music_info.clone())),
+                style::ButtonType::Info.default(),
+            ),
+            "加入待播",
+            iced::widget::tooltip::Position::Top,
+        );
+
         let title = util::get_title(music_info);
+        let unplayable = self
+            .track_store
+            .get(&music_info.path)
+            .and_then(|record| record.unplayable.as_deref())
+            .map(|reason| {
+                tooltip_text(
+                    text("无法播放")
+                        .size(14)
+                        .style(theme::Text::Color(self.setting.get_theme().palette().danger)),
+                    reason,
+                    iced::widget::tooltip::Position::Top,
+                )
+            });
 
         column!(
             text(title).size(22),
-            row![play_btn, list_btn, text(&music_info.artist).size(16),]
-                .align_items(Alignment::Center)
-                .spacing(5),
+            row![
+                play_btn,
+                list_btn,
+                play_next_btn,
+                queue_btn,
+                text(&music_info.artist).size(16),
+                text(self.resume_label(&music_info.path).unwrap_or_default()).size(14),
+            ]
+            .push_maybe(unplayable)
+            .align_items(Alignment::Center)
+            .spacing(5),
         )
         .spacing(5)
         .into()
@@ -930,11 +1095,24 @@ impl SilkPlayer {
                 style::ContainerStyle::Border(2.0),
             )));
 
-        let left_album = container(img)
-            .width(Length::FillPortion(1))
-            .height(Length::Fill)
-            .center_y()
-            .center_x();
+        let visualizer = (self.setting.visualizer != VisualStyle::Off).then(|| {
+            self.visualizer.view(
+                self.setting.visualizer,
+                &self.current_song.album_color,
+                img_size,
+                img_size / 3.,
+            )
+        });
+        let left_album = container(
+            column!(img)
+                .push_maybe(visualizer)
+                .spacing(20)
+                .align_items(Alignment::Center),
+        )
+        .width(Length::FillPortion(1))
+        .height(Length::Fill)
+        .center_y()
+        .center_x();
 
         let title = util::get_title(&self.current_song);
         let title = Text::new(title).size(26);
@@ -945,11 +1123,25 @@ impl SilkPlayer {
             button(text("信息").size(14))
                 .on_press(Message::ChangeDetail(DetailTab::Info))
                 .style(theme::Button::Custom(Box::new(ButtonType::Info.default()))),
+            button(text(format!("待播 {}", self.app_control.up_next.len())).size(14))
+                .on_press(Message::ChangeDetail(DetailTab::UpNext))
+                .style(theme::Button::Custom(Box::new(ButtonType::Info.default()))),
+            button(text(format!("书签 {}", self.bookmarks().len())).size(14))
+                .on_press(Message::ChangeDetail(DetailTab::Bookmarks))
+                .style(theme::Button::Custom(Box::new(ButtonType::Info.default()))),
         )
+        .push_maybe((!self.current_song.chapters.is_empty()).then(|| {
+            button(text("章节").size(14))
+                .on_press(Message::ChangeDetail(DetailTab::Chapters))
+                .style(theme::Button::Custom(Box::new(ButtonType::Info.default())))
+        }))
         .padding(10);
         let detail = match self.detail_tab {
             DetailTab::Lyric => self.detail_lyrics(&self.current_song.lyric),
             DetailTab::Info => self.detail_info(),
+            DetailTab::UpNext => self.detail_up_next(),
+            DetailTab::Chapters => self.detail_chapters(),
+            DetailTab::Bookmarks => self.detail_bookmarks(),
         };
         let detail = container(detail)
             .center_x()
@@ -1051,6 +1243,81 @@ impl SilkPlayer {
             .into()
     }
 
+    /// 待播队列，播完后继续播放列表
+    fn detail_up_next(&self) -> View<'_> {
+        let up_next = &self.app_control.up_next;
+        let mut list = column!().spacing(10);
+        for (index, music_info) in up_next.iter().enumerate() {
+            list = list.push(
+                row!(
+                    text(format!("{:>2}", index + 1)).size(16),
+                    text(util::get_title(music_info)).size(18).width(Length::Fill),
+                    text(&music_info.artist).size(14),
+                    button_icon(
+                        "close",
+                        14.,
+                        Message::SongControl(SongControl::Dequeue(index)),
+                        ButtonType::Info.default(),
+                    ),
+                )
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+        if up_next.is_empty() {
+            list = list.push(text("待播队列为空，播放列表按原顺序继续").size(16));
+        }
+
+        let clear = button(text("清空").size(14))
+            .on_press_maybe(
+                (!up_next.is_empty()).then_some(Message::SongControl(SongControl::ClearQueue)),
+            )
+            .style(theme::Button::Custom(Box::new(ButtonType::Info.default())));
+
+        container(column!(clear, Scrollable::new(list).width(Length::Fill)).spacing(10))
+            .padding(30)
+            .style(theme::Container::Custom(Box::new(
+                style::ContainerStyle::BackgroundWithAlpha(0.2),
+            )))
+            .into()
+    }
+
+    /// 章节列表，点击跳转
+    fn detail_chapters(&self) -> View<'_> {
+        let current = self.current_chapter();
+        let mut list = column!().spacing(5);
+        for (index, chapter) in self.current_song.chapters.iter().enumerate() {
+            let style = if Some(index) == current {
+                ButtonType::Primary.default()
+            } else {
+                ButtonType::Text.default()
+            };
+            list = list.push(
+                button(
+                    row!(
+                        text(util::play_time(chapter.start)).size(14),
+                        text(&chapter.title).size(18),
+                    )
+                    .spacing(15)
+                    .align_items(Alignment::Center),
+                )
+                .width(Length::Fill)
+                .on_press(Message::SongControl(SongControl::SeekTo(chapter.start)))
+                .style(theme::Button::Custom(Box::new(style))),
+            );
+        }
+        if self.current_song.chapters.is_empty() {
+            list = list.push(text("没有章节").size(16));
+        }
+
+        container(Scrollable::new(list).width(Length::Fill))
+            .padding(30)
+            .style(theme::Container::Custom(Box::new(
+                style::ContainerStyle::BackgroundWithAlpha(0.2),
+            )))
+            .into()
+    }
+
     fn detail_lyrics(&self, lyrics: &Vec<ShowLyric>) -> View {
         let mut col = Column::new()
             .width(Length::Fill)
@@ -1147,3 +1414,24 @@ impl SilkPlayer {
             .into()
     }
 }
+
+impl<E: PlayEngine> SilkPlayer<E> {
+    pub fn get_list_by_tag(&self) -> Vec<MusicInfo> {
+        let mut list = vec![];
+        // let mut list = column!(detail).spacing(15);
+        if let Ok(all_list) = self.music_list.all_list.try_lock() {
+            for music_info in all_list.iter() {
+                let tag_names: Vec<_> = music_info
+                    .tags
+                    .iter()
+                    .filter(|item| item.name.eq(&self.tag.name))
+                    .collect();
+                if tag_names.is_empty() {
+                    continue;
+                }
+                list.push(music_info.clone());
+            }
+        }
+        list
+    }
+}
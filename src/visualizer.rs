@@ -0,0 +1,332 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use iced::{
+    mouse,
+    widget::canvas::{self, Canvas, Frame, Geometry, Path, Stroke},
+    Color, Length, Point, Rectangle, Renderer, Size, Theme, Vector,
+};
+use kira::clock::clock_info::ClockInfoProvider;
+use kira::effect::{Effect, EffectBuilder};
+use kira::modulator::value_provider::ModulatorValueProvider;
+use kira::Frame as AudioFrame;
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use serde::{Deserialize, Serialize};
+
+use crate::{Message, View};
+
+pub const FRAME_SECS: f32 = 1. / 30.; // 可视化刷新间隔，单位：s
+const FFT_SIZE: usize = 2048;
+const BAR_COUNT: usize = 48;
+const WAVE_POINTS: usize = 256;
+const MIN_FREQ: f32 = 40.;
+const MAX_FREQ: f32 = 16000.;
+const MIN_DB: f32 = -60.; // 低于此响度的频段不显示
+const FALL: f32 = 0.85; // 每帧回落的比例，柱条平滑下降
+
+/// 可视化样式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VisualStyle {
+    Off,
+    #[default]
+    Bars,
+    Wave,
+    Circle,
+}
+impl VisualStyle {
+    pub const ALL: [Self; 4] = [
+        VisualStyle::Off,
+        VisualStyle::Bars,
+        VisualStyle::Wave,
+        VisualStyle::Circle,
+    ];
+}
+impl std::fmt::Display for VisualStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                VisualStyle::Off => "关闭",
+                VisualStyle::Bars => "柱状",
+                VisualStyle::Wave => "波形",
+                VisualStyle::Circle => "环形",
+            }
+        )
+    }
+}
+
+/// 采样分接器，挂在效果音轨最后，把输出的采样写入环形缓冲区供界面读取，声音原样通过
+#[derive(Clone)]
+pub struct SpectrumTap(Arc<TapRing>);
+
+struct TapRing {
+    enabled: AtomicBool,
+    sample_rate: AtomicU32,
+    write: AtomicUsize,
+    samples: Vec<AtomicU32>, // 左右声道平均后的采样
+}
+
+impl Default for SpectrumTap {
+    fn default() -> Self {
+        Self(Arc::new(TapRing {
+            enabled: AtomicBool::new(false),
+            sample_rate: AtomicU32::new(44100),
+            write: AtomicUsize::new(0),
+            samples: (0..FFT_SIZE).map(|_| AtomicU32::new(0)).collect(),
+        }))
+    }
+}
+
+impl SpectrumTap {
+    /// 关闭时不再写入采样
+    pub fn set_enabled(&self, enabled: bool) {
+        self.0.enabled.store(enabled, Ordering::Relaxed);
+    }
+    pub fn sample_rate(&self) -> u32 {
+        self.0.sample_rate.load(Ordering::Relaxed)
+    }
+    /// 最近的 FFT_SIZE 个采样，按时间先后排列
+    pub fn samples(&self) -> Vec<f32> {
+        let ring = &self.0;
+        let write = ring.write.load(Ordering::Relaxed);
+        (0..FFT_SIZE)
+            .map(|index| f32::from_bits(ring.samples[(write + index) % FFT_SIZE].load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+impl EffectBuilder for SpectrumTap {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (Box::new(self), ())
+    }
+}
+
+impl Effect for SpectrumTap {
+    fn init(&mut self, sample_rate: u32) {
+        self.on_change_sample_rate(sample_rate);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.0.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn process(
+        &mut self,
+        input: AudioFrame,
+        _dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> AudioFrame {
+        let ring = &self.0;
+        if ring.enabled.load(Ordering::Relaxed) {
+            let write = ring.write.load(Ordering::Relaxed);
+            let sample = (input.left + input.right) / 2.;
+            ring.samples[write].store(sample.to_bits(), Ordering::Relaxed);
+            ring.write.store((write + 1) % FFT_SIZE, Ordering::Relaxed);
+        }
+        input
+    }
+}
+
+/// 可视化数据：对数分布的频段响度和最近一段波形
+pub struct Visualizer {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>, // 汉宁窗
+    levels: Vec<f32>, // 各频段响度，0~1
+    wave: Vec<f32>,
+}
+
+impl Default for Visualizer {
+    fn default() -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|index| {
+                let phase = std::f32::consts::TAU * index as f32 / (FFT_SIZE - 1) as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+        Self {
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            window,
+            levels: vec![0.; BAR_COUNT],
+            wave: vec![0.; WAVE_POINTS],
+        }
+    }
+}
+
+impl Visualizer {
+    /// 还有柱条没有回落到底，暂停后仍需继续刷新
+    pub fn is_active(&self) -> bool {
+        self.levels.iter().any(|level| *level > 0.01)
+    }
+
+    /// 用最新的采样计算频谱
+    pub fn update(&mut self, samples: &[f32], sample_rate: u32) {
+        if samples.len() < FFT_SIZE {
+            return;
+        }
+        self.wave = samples[FFT_SIZE - WAVE_POINTS * 2..]
+            .iter()
+            .step_by(2)
+            .copied()
+            .collect();
+
+        let mut buffer: Vec<Complex<f32>> = samples
+            .iter()
+            .zip(&self.window)
+            .map(|(sample, weight)| Complex::new(sample * weight, 0.))
+            .collect();
+        self.fft.process(&mut buffer);
+
+        let bin_hz = sample_rate as f32 / FFT_SIZE as f32;
+        let scale = FFT_SIZE as f32 / 4.; // 满幅正弦加汉宁窗后的峰值
+        let ratio = (MAX_FREQ / MIN_FREQ).powf(1. / BAR_COUNT as f32);
+        for (index, level) in self.levels.iter_mut().enumerate() {
+            let low = MIN_FREQ * ratio.powi(index as i32);
+            let to = (((low * ratio) / bin_hz) as usize).min(FFT_SIZE / 2);
+            let from = ((low / bin_hz) as usize).max(1).min(to.saturating_sub(1));
+            let magnitude = buffer[from..to.max(from + 1)]
+                .iter()
+                .map(|value| value.norm())
+                .fold(0., f32::max);
+            let db = 20. * (magnitude / scale).max(1e-6).log10();
+            let value = ((db - MIN_DB) / -MIN_DB).clamp(0., 1.);
+            *level = value.max(*level * FALL);
+        }
+    }
+
+    /// 没有声音时柱条逐帧回落，波形归零
+    pub fn fall(&mut self) {
+        for level in self.levels.iter_mut() {
+            *level *= FALL;
+        }
+        self.wave.iter_mut().for_each(|sample| *sample = 0.);
+    }
+
+    /// 颜色取自专辑配色，为空时使用主题文字颜色
+    pub fn view<'a>(&'a self, style: VisualStyle, colors: &'a [Color], width: f32, height: f32) -> View<'a> {
+        Canvas::new(VisualizerCanvas {
+            visualizer: self,
+            style,
+            colors,
+        })
+        .width(Length::Fixed(width))
+        .height(Length::Fixed(height))
+        .into()
+    }
+}
+
+struct VisualizerCanvas<'a> {
+    visualizer: &'a Visualizer,
+    style: VisualStyle,
+    colors: &'a [Color],
+}
+
+impl<'a> VisualizerCanvas<'a> {
+    /// 按位置（0~1）在专辑配色间过渡，专辑配色同时用作背景，向文字颜色靠拢以保证对比度
+    fn color_at(&self, ratio: f32, text: Color) -> Color {
+        let base = match self.colors.len() {
+            0 => text,
+            len => {
+                let pos = ratio.clamp(0., 1.) * (len - 1) as f32;
+                let index = pos.floor() as usize;
+                mix(self.colors[index], self.colors[(index + 1).min(len - 1)], pos.fract())
+            }
+        };
+        Color {
+            a: 0.85,
+            ..mix(base, text, 0.5)
+        }
+    }
+}
+
+impl<'a> canvas::Program<Message> for VisualizerCanvas<'a> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let text = theme.palette().text;
+        let (width, height) = (frame.width(), frame.height());
+        let levels = &self.visualizer.levels;
+        let ratio_of = |index: usize, len: usize| index as f32 / (len.max(2) - 1) as f32;
+
+        match self.style {
+            VisualStyle::Off => {}
+            VisualStyle::Bars => {
+                let gap = width / levels.len() as f32;
+                let bar_width = gap * 0.7;
+                for (index, level) in levels.iter().enumerate() {
+                    let bar_height = (level * height).max(1.);
+                    frame.fill_rectangle(
+                        Point::new(index as f32 * gap + (gap - bar_width) / 2., height - bar_height),
+                        Size::new(bar_width, bar_height),
+                        self.color_at(ratio_of(index, levels.len()), text),
+                    );
+                }
+            }
+            VisualStyle::Wave => {
+                let wave = &self.visualizer.wave;
+                let path = Path::new(|builder| {
+                    for (index, sample) in wave.iter().enumerate() {
+                        let point = Point::new(
+                            ratio_of(index, wave.len()) * width,
+                            height / 2. * (1. - sample.clamp(-1., 1.)),
+                        );
+                        if index == 0 {
+                            builder.move_to(point);
+                        } else {
+                            builder.line_to(point);
+                        }
+                    }
+                });
+                frame.stroke(
+                    &path,
+                    Stroke::default()
+                        .with_width(2.)
+                        .with_color(self.color_at(0.5, text)),
+                );
+            }
+            VisualStyle::Circle => {
+                let center = frame.center();
+                let radius = width.min(height) * 0.25;
+                let max_len = width.min(height) * 0.5 - radius;
+                frame.stroke(
+                    &Path::circle(center, radius),
+                    Stroke::default()
+                        .with_width(1.)
+                        .with_color(Color { a: 0.4, ..self.color_at(0., text) }),
+                );
+                for (index, level) in levels.iter().enumerate() {
+                    let angle = std::f32::consts::TAU * index as f32 / levels.len() as f32
+                        - std::f32::consts::FRAC_PI_2;
+                    let direction = Vector::new(angle.cos(), angle.sin());
+                    let len = (level * max_len).max(1.);
+                    frame.stroke(
+                        &Path::line(center + direction * radius, center + direction * (radius + len)),
+                        Stroke::default()
+                            .with_width(3.)
+                            .with_color(self.color_at(ratio_of(index, levels.len()), text)),
+                    );
+                }
+            }
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    Color::from_rgb(
+        a.r + (b.r - a.r) * t,
+        a.g + (b.g - a.g) * t,
+        a.b + (b.b - a.b) * t,
+    )
+}
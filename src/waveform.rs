@@ -0,0 +1,184 @@
+use iced::{
+    mouse,
+    widget::canvas::{self, event, Canvas, Event, Frame, Geometry},
+    Color, Length, Point, Rectangle, Renderer, Size, Theme,
+};
+
+use crate::{loudness, util, Message, View};
+
+const PEAK_COUNT: usize = 400; // 波形的采样点数
+const HEIGHT: f32 = 24.;
+
+/// 波形缓存文件，与专辑配色数据一样放在缓存目录，按路径和修改时间区分，文件变化后重新生成
+fn cache_path(path: &str) -> Option<String> {
+    let modified = loudness::modified(path)?;
+    let key = fnv1a(path.bytes().chain(modified.to_le_bytes()));
+    Some(format!("{}/{:016x}_wave.json", util::cache_dir(), key))
+}
+
+/// FNV-1a 哈希，结果不随 Rust 版本变化，升级后缓存仍然有效
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// 读取缓存的波形，还未生成时返回 None，无法解码的歌曲返回空列表
+pub fn cached(path: &str) -> Option<Vec<f32>> {
+    let data = std::fs::read_to_string(cache_path(path)?).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+/// 解码整首歌曲，按时长分段取峰值并归一化后写入缓存，在线程池中执行
+pub fn generate(path: &str, duration: f32) {
+    let cache_path = match cache_path(path) {
+        Some(cache_path) => cache_path,
+        None => return,
+    };
+    let mut peaks = vec![0f32; PEAK_COUNT];
+    let mut frame_index = 0u64;
+    let result = loudness::decode(path, |samples, channels, rate| {
+        let total = (duration as f64 * rate as f64).max(1.);
+        for frame in samples.chunks(channels.max(1)) {
+            let index = ((frame_index as f64 / total * PEAK_COUNT as f64) as usize).min(PEAK_COUNT - 1);
+            let peak = frame.iter().fold(0f32, |peak, sample| peak.max(sample.abs()));
+            peaks[index] = peaks[index].max(peak);
+            frame_index += 1;
+        }
+        true
+    });
+    if let Err(err) = result {
+        // 无法解码的也写入空结果，避免反复尝试
+        util::log_err(format!("gen waveform error path={} err={}", path, err));
+        peaks.clear();
+    }
+    let max = peaks.iter().fold(0f32, |max, peak| max.max(*peak)).max(1e-3);
+    peaks.iter_mut().for_each(|peak| *peak /= max);
+    if let Ok(data) = serde_json::to_string(&peaks) {
+        if let Err(err) = util::write_file(cache_path, data) {
+            util::log_err(format!("save waveform error path={} err={}", path, err));
+        }
+    }
+}
+
+/// 波形进度条，点击、拖拽与滑块一致：拖动时发送 UpdateSongTime，松开时发送 UpdateSongTimeRelease
+pub fn seek_bar(peaks: &[f32], value: f32, duration: f32) -> View<'_> {
+    Canvas::new(SeekBar {
+        peaks,
+        value,
+        duration,
+    })
+    .width(Length::Fill)
+    .height(HEIGHT)
+    .into()
+}
+
+struct SeekBar<'a> {
+    peaks: &'a [f32],
+    value: f32,
+    duration: f32,
+}
+
+impl<'a> SeekBar<'a> {
+    fn value_at(&self, bounds: Rectangle, x: f32) -> f32 {
+        ((x - bounds.x) / bounds.width).clamp(0., 1.) * self.duration
+    }
+}
+
+impl<'a> canvas::Program<Message> for SeekBar<'a> {
+    type State = bool; // 是否正在拖拽
+
+    fn update(
+        &self,
+        dragging: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let Event::Mouse(event) = event else {
+            return (event::Status::Ignored, None);
+        };
+        match event {
+            mouse::Event::ButtonPressed(mouse::Button::Left) => {
+                if let Some(position) = cursor.position_over(bounds) {
+                    *dragging = true;
+                    let value = self.value_at(bounds, position.x);
+                    return (event::Status::Captured, Some(Message::UpdateSongTime(value)));
+                }
+            }
+            mouse::Event::CursorMoved { position } if *dragging => {
+                let value = self.value_at(bounds, position.x);
+                return (event::Status::Captured, Some(Message::UpdateSongTime(value)));
+            }
+            mouse::Event::ButtonReleased(mouse::Button::Left) if *dragging => {
+                *dragging = false;
+                return (event::Status::Captured, Some(Message::UpdateSongTimeRelease));
+            }
+            _ => {}
+        }
+        (event::Status::Ignored, None)
+    }
+
+    fn draw(
+        &self,
+        _dragging: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+        let (width, height) = (frame.width(), frame.height());
+        let played = if self.duration > 0. {
+            (self.value / self.duration).clamp(0., 1.) * width
+        } else {
+            0.
+        };
+        let step = width / self.peaks.len().max(1) as f32;
+        for (index, peak) in self.peaks.iter().enumerate() {
+            let x = index as f32 * step;
+            let bar_height = (peak * height).max(1.);
+            let color = if x < played {
+                palette.primary
+            } else {
+                Color {
+                    a: 0.35,
+                    ..palette.text
+                }
+            };
+            frame.fill_rectangle(
+                Point::new(x, (height - bar_height) / 2.),
+                Size::new((step * 0.8).max(1.), bar_height),
+                color,
+            );
+        }
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        dragging: &Self::State,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        if *dragging || cursor.is_over(bounds) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_is_stable() {
+        // FNV-1a 的公开测试向量，哈希结果变化会让已有缓存全部失效
+        assert_eq!(fnv1a(*b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(*b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(*b"foobar"), 0x85944171f73967e8);
+    }
+}